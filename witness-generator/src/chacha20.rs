@@ -0,0 +1,218 @@
+//! ChaCha20 ([RFC 8439](https://www.rfc-editor.org/rfc/rfc8439)) and the bit layout
+//! `PlaintextAuthentication` takes its key, nonce and counter in.
+
+use super::*;
+
+/// Size of a ChaCha20 keystream block, each block uses the next counter.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Bits of a 32-bit word, most significant bit first.
+pub type WordBits = [u8; 32];
+
+/// The `key`, `nonce` and `counter` inputs of `PlaintextAuthentication`.
+///
+/// The key and nonce are read as little-endian 32-bit words as in RFC 8439, and every word is given
+/// as its bits, most significant bit first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChaCha20Input {
+  pub key:     [WordBits; 8],
+  pub nonce:   [WordBits; 3],
+  pub counter: WordBits,
+}
+
+impl ChaCha20Input {
+  pub fn new(key: &[u8; 32], nonce: &[u8; 12], counter: u32) -> Self {
+    Self {
+      key:     le_words(key).map(word_bits),
+      nonce:   le_words(nonce).map(word_bits),
+      counter: word_bits(counter),
+    }
+  }
+}
+
+/// Bits of `word`, most significant bit first.
+pub fn word_bits(word: u32) -> WordBits {
+  std::array::from_fn(|idx| (word >> (31 - idx) & 1) as u8)
+}
+
+fn le_words<const N: usize>(bytes: &[u8]) -> [u32; N] {
+  std::array::from_fn(|idx| {
+    u32::from_le_bytes([bytes[4 * idx], bytes[4 * idx + 1], bytes[4 * idx + 2], bytes[4 * idx + 3]])
+  })
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+  state[a] = state[a].wrapping_add(state[b]);
+  state[d] = (state[d] ^ state[a]).rotate_left(16);
+  state[c] = state[c].wrapping_add(state[d]);
+  state[b] = (state[b] ^ state[c]).rotate_left(12);
+  state[a] = state[a].wrapping_add(state[b]);
+  state[d] = (state[d] ^ state[a]).rotate_left(8);
+  state[c] = state[c].wrapping_add(state[d]);
+  state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// The ChaCha20 block function, i.e. the keystream block for `counter`.
+fn block(key: &[u8; 32], nonce: &[u8; 12], counter: u32) -> [u8; BLOCK_SIZE] {
+  let key = le_words::<8>(key);
+  let nonce = le_words::<3>(nonce);
+  let mut initial = [0; 16];
+  initial[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+  initial[4..12].copy_from_slice(&key);
+  initial[12] = counter;
+  initial[13..].copy_from_slice(&nonce);
+
+  let mut state = initial;
+  for _ in 0..10 {
+    quarter_round(&mut state, 0, 4, 8, 12);
+    quarter_round(&mut state, 1, 5, 9, 13);
+    quarter_round(&mut state, 2, 6, 10, 14);
+    quarter_round(&mut state, 3, 7, 11, 15);
+    quarter_round(&mut state, 0, 5, 10, 15);
+    quarter_round(&mut state, 1, 6, 11, 12);
+    quarter_round(&mut state, 2, 7, 8, 13);
+    quarter_round(&mut state, 3, 4, 9, 14);
+  }
+
+  let mut output = [0; BLOCK_SIZE];
+  for (idx, word) in
+    state.iter().zip(initial).map(|(word, init)| word.wrapping_add(init)).enumerate()
+  {
+    output[4 * idx..4 * idx + 4].copy_from_slice(&word.to_le_bytes());
+  }
+  output
+}
+
+/// Keystream bytes for `len` bytes starting at block `counter`.
+fn keystream(key: &[u8; 32], nonce: &[u8; 12], counter: u32, len: usize) -> Vec<u8> {
+  (0..len.div_ceil(BLOCK_SIZE))
+    .flat_map(|idx| block(key, nonce, counter.wrapping_add(idx as u32)))
+    .take(len)
+    .collect()
+}
+
+/// Encrypts `plaintext` with the keystream starting at block `counter`.
+pub fn encrypt(key: &[u8; 32], nonce: &[u8; 12], counter: u32, plaintext: &[u8]) -> Vec<u8> {
+  plaintext
+    .iter()
+    .zip(keystream(key, nonce, counter, plaintext.len()))
+    .map(|(byte, key_byte)| byte ^ key_byte)
+    .collect()
+}
+
+/// Decrypts `ciphertext` with the keystream starting at block `counter`.
+pub fn decrypt(key: &[u8; 32], nonce: &[u8; 12], counter: u32, ciphertext: &[u8]) -> Vec<u8> {
+  encrypt(key, nonce, counter, ciphertext)
+}
+
+/// Encrypts a padded plaintext chunk as `PlaintextAuthentication` does: padding keeps its position
+/// in the keystream and stays padding in the ciphertext.
+pub fn encrypt_padded(
+  key: &[u8; 32],
+  nonce: &[u8; 12],
+  counter: u32,
+  plaintext: &[ByteOrPad],
+) -> Vec<ByteOrPad> {
+  plaintext
+    .iter()
+    .zip(keystream(key, nonce, counter, plaintext.len()))
+    .map(|(byte, key_byte)| match byte {
+      ByteOrPad::Byte(byte) => ByteOrPad::Byte(byte ^ key_byte),
+      ByteOrPad::Pad => ByteOrPad::Pad,
+    })
+    .collect()
+}
+
+/// Counter of the `fold`-th `data_bytes` chunk of a plaintext whose first chunk starts at block
+/// `counter`. Each fold uses `data_bytes / 64` blocks.
+pub fn fold_counter(
+  counter: u32,
+  data_bytes: usize,
+  fold: usize,
+) -> Result<u32, WitnessGeneratorError> {
+  if !data_bytes.is_multiple_of(BLOCK_SIZE) {
+    return Err(WitnessGeneratorError::CatchAll(format!(
+      "data bytes {data_bytes} is not a multiple of the chacha20 block size {BLOCK_SIZE}"
+    )));
+  }
+  u32::try_from(fold * data_bytes / BLOCK_SIZE)
+    .ok()
+    .and_then(|blocks| counter.checked_add(blocks))
+    .ok_or_else(|| WitnessGeneratorError::CatchAll("chacha20 counter overflow".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Section 2.4.2 of RFC 8439
+  const RFC_KEY: [u8; 32] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+  ];
+  const RFC_NONCE: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+  const RFC_PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+    only one tip for the future, sunscreen would be it.";
+  const RFC_CIPHERTEXT: [u8; 114] = [
+    0x6e, 0x2e, 0x35, 0x9a, 0x25, 0x68, 0xf9, 0x80, 0x41, 0xba, 0x07, 0x28, 0xdd, 0x0d, 0x69, 0x81,
+    0xe9, 0x7e, 0x7a, 0xec, 0x1d, 0x43, 0x60, 0xc2, 0x0a, 0x27, 0xaf, 0xcc, 0xfd, 0x9f, 0xae, 0x0b,
+    0xf9, 0x1b, 0x65, 0xc5, 0x52, 0x47, 0x33, 0xab, 0x8f, 0x59, 0x3d, 0xab, 0xcd, 0x62, 0xb3, 0x57,
+    0x16, 0x39, 0xd6, 0x24, 0xe6, 0x51, 0x52, 0xab, 0x8f, 0x53, 0x0c, 0x35, 0x9f, 0x08, 0x61, 0xd8,
+    0x07, 0xca, 0x0d, 0xbf, 0x50, 0x0d, 0x6a, 0x61, 0x56, 0xa3, 0x8e, 0x08, 0x8a, 0x22, 0xb6, 0x5e,
+    0x52, 0xbc, 0x51, 0x4d, 0x16, 0xcc, 0xf8, 0x06, 0x81, 0x8c, 0xe9, 0x1a, 0xb7, 0x79, 0x37, 0x36,
+    0x5a, 0xf9, 0x0b, 0xbf, 0x74, 0xa3, 0x5b, 0xe6, 0xb4, 0x0b, 0x8e, 0xed, 0xf2, 0x78, 0x5e, 0x42,
+    0x87, 0x4d,
+  ];
+
+  #[test]
+  fn test_rfc8439_encrypt() {
+    assert_eq!(encrypt(&RFC_KEY, &RFC_NONCE, 1, RFC_PLAINTEXT), RFC_CIPHERTEXT);
+    assert_eq!(decrypt(&RFC_KEY, &RFC_NONCE, 1, &RFC_CIPHERTEXT), RFC_PLAINTEXT);
+  }
+
+  #[test]
+  fn test_encrypt_mock_response() {
+    assert_eq!(
+      encrypt(&[0; 32], &RFC_NONCE, 1, RESPONSE_PLAINTEXT.as_bytes()),
+      RESPONSE_CIPHERTEXT
+    );
+  }
+
+  #[test]
+  fn test_encrypt_padded_folds() {
+    const DATA_BYTES: usize = 128;
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let ciphertext = plaintext
+      .chunks(DATA_BYTES)
+      .enumerate()
+      .flat_map(|(fold, chunk)| {
+        let counter = fold_counter(1, DATA_BYTES, fold).unwrap();
+        let padded = ByteOrPad::from_bytes_with_padding(chunk, DATA_BYTES - chunk.len());
+        encrypt_padded(&[0; 32], &RFC_NONCE, counter, &padded)
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(ciphertext.len(), 3 * DATA_BYTES);
+    assert_eq!(&ciphertext[..RESPONSE_CIPHERTEXT.len()], RESPONSE_CIPHERTEXT.map(ByteOrPad::Byte));
+    assert!(ciphertext[RESPONSE_CIPHERTEXT.len()..].iter().all(|byte| *byte == ByteOrPad::Pad));
+  }
+
+  #[test]
+  fn test_fold_counter() {
+    assert_eq!(fold_counter(1, 1024, 0).unwrap(), 1);
+    assert_eq!(fold_counter(1, 1024, 2).unwrap(), 33);
+    assert!(fold_counter(1, 100, 1).is_err());
+    assert!(fold_counter(u32::MAX, 64, 1).is_err());
+  }
+
+  #[test]
+  fn test_input_bits() {
+    let input = ChaCha20Input::new(&RFC_KEY, &RFC_NONCE, 1);
+    // First key word is 0x03020100, so its bits start with the last key byte
+    assert_eq!(input.key[0], word_bits(0x0302_0100));
+    assert_eq!(input.key[0][..8], [0, 0, 0, 0, 0, 0, 1, 1]);
+    assert_eq!(input.key[0][24..], [0; 8]);
+    assert_eq!(input.nonce[1], word_bits(0x4a00_0000));
+    assert_eq!(input.counter[..31], [0; 31]);
+    assert_eq!(input.counter[31], 1);
+  }
+}
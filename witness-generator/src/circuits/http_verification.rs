@@ -0,0 +1,402 @@
+use super::*;
+use crate::http::{parser::StreamingParser, HttpStatus, LineEndings, RawHttpMachine};
+
+const CR: u8 = 13;
+const LF: u8 = 10;
+
+/// Computes the `step_out` of `HTTPVerification` for one plaintext chunk.
+///
+/// # Arguments
+/// - `step_in`: public IO of the fold
+/// - `machine_state`: HTTP machine state at the start of `data`, whose digest is `step_in[3]`
+/// - `data`: the plaintext chunk, padded with [`ByteOrPad::Pad`] to `DATA_BYTES`
+/// - `main_digests`: digests of the start line and headers to lock, zero for unused entries
+/// - `ciphertext_digest`: digest of all ciphertext chunks
+/// # Returns
+/// - the `step_out` of the fold
+/// - the machine state at the end of `data`, the `machine_state` of the next fold
+///
+/// Padding is expected only at the end of the plaintext. The circuit reads it as a byte other than
+/// SP, CR, LF and colon: it clears `line_status`, and right after a CRLF it starts a header line.
+/// After a CR the state is then no parser state, so no fold can follow.
+pub fn http_verification(
+  step_in: PublicIo,
+  machine_state: RawHttpMachine,
+  data: &[ByteOrPad],
+  main_digests: &[F],
+  ciphertext_digest: F,
+) -> Result<(PublicIo, RawHttpMachine), WitnessGeneratorError> {
+  if data.is_empty() {
+    return Err(WitnessGeneratorError::CircuitConstraint("no data to verify".to_string()));
+  }
+  if step_in.http_machine_state_digest
+    != field_polynomial_digest(&machine_state.flatten(), ciphertext_digest)
+  {
+    return Err(WitnessGeneratorError::CircuitConstraint(
+      "step_in[3] is not the digest of the machine state".to_string(),
+    ));
+  }
+  let header_verification_lock = main_digests
+    .iter()
+    .filter(|digest| **digest != F::ZERO)
+    .map(|digest| poseidon::<1>(&[*digest]))
+    .sum::<F>();
+  if step_in.header_verification_lock != header_verification_lock {
+    return Err(WitnessGeneratorError::CircuitConstraint(
+      "step_in[4] is not the hashed sum of the main digests".to_string(),
+    ));
+  }
+
+  let (plaintext_digest, http_monomial) =
+    data_digest_with_counter(data, ciphertext_digest, step_in.http_monomial);
+
+  // Parser state after each byte, resuming mid-line when the previous fold ended inside a line.
+  // Only the parser status is used, line digests and monomials are tracked below the way the
+  // circuit does.
+  let parser = StreamingParser::resume(machine_state, ciphertext_digest, LineEndings::Strict);
+  let mut parser = parser.map_err(|err| match err {
+    WitnessGeneratorError::InvalidHttpMachine(reason) => WitnessGeneratorError::CircuitConstraint(
+      format!("machine state is not a parser state: {reason}"),
+    ),
+    err => err,
+  })?;
+  let machine = parser.machine();
+  let bytes = data
+    .iter()
+    .filter_map(|byte| match byte {
+      ByteOrPad::Byte(byte) => Some(*byte),
+      ByteOrPad::Pad => None,
+    })
+    .collect::<Vec<_>>();
+  let mut parsed = parser.feed(&bytes)?.into_iter();
+  let mut states = Vec::with_capacity(data.len());
+  let mut state = machine;
+  for byte in data {
+    if let (ByteOrPad::Byte(_), Some(next)) = (byte, parsed.next()) {
+      state = next;
+    }
+    states.push(state);
+  }
+  // `parsing_body` before reading `data[idx]`
+  let parsing_body = |idx: usize| {
+    let state = if idx == 0 { machine } else { states[idx - 1] };
+    state.status == HttpStatus::ParsingBody
+  };
+  let is_padding = |idx: usize| matches!(data[idx], ByteOrPad::Pad);
+  let zeroed_data = |idx: usize| match data[idx] {
+    ByteOrPad::Byte(byte) => F::from(u64::from(byte)),
+    ByteOrPad::Pad => F::ZERO,
+  };
+
+  // Monomials of the line being digested, zero on line changes and in the body
+  let mut main_monomials = vec![machine_state.line_monomial];
+  for idx in 0..data.len() - 1 {
+    let monomial = main_monomials[idx];
+    let is_line_change = data[idx + 1] == CR || data[idx + 1] == LF;
+    main_monomials.push(if parsing_body(idx + 1) || is_line_change {
+      F::ZERO
+    } else if monomial == F::ZERO {
+      F::ONE
+    } else {
+      monomial * ciphertext_digest
+    });
+  }
+
+  let mut line_digest = machine_state.line_digest;
+  let mut num_matched = 0;
+  for (byte, monomial) in data.iter().zip(&main_monomials) {
+    if *monomial == F::ZERO {
+      line_digest = F::ZERO;
+    }
+    line_digest += F::from(byte) * monomial;
+    if line_digest != F::ZERO && main_digests.contains(&line_digest) {
+      num_matched += 1;
+    }
+  }
+
+  // The body monomial starts at one on the first body byte and is carried across folds
+  let body_ctr_is_zero = step_in.body_monomial == F::ZERO;
+  let mut body_monomial = step_in.body_monomial * ciphertext_digest
+    + if body_ctr_is_zero && parsing_body(0) { F::ONE } else { F::ZERO };
+  let mut body_ctr = u64::from(!body_ctr_is_zero || parsing_body(0));
+  let mut body_digest = body_monomial * zeroed_data(0);
+  for idx in 1..data.len() {
+    if parsing_body(idx) && !is_padding(idx) {
+      body_ctr += 1;
+    }
+    if !is_padding(idx) {
+      body_monomial =
+        body_monomial * ciphertext_digest + if body_ctr == 1 { F::ONE } else { F::ZERO };
+    }
+    body_digest += body_monomial * zeroed_data(idx);
+  }
+
+  let mut next_machine_state = RawHttpMachine {
+    line_digest,
+    line_monomial: main_monomials[data.len() - 1] * ciphertext_digest,
+    ..parser.checkpoint()
+  };
+  if data.iter().any(|byte| matches!(byte, ByteOrPad::Pad)) {
+    if next_machine_state.line_status == F::from(2) {
+      next_machine_state.parsing_header += F::ONE;
+      next_machine_state.parsing_field_name += F::ONE;
+    }
+    next_machine_state.line_status = F::ZERO;
+  }
+
+  Ok((
+    PublicIo {
+      accumulator: step_in.accumulator - plaintext_digest + body_digest,
+      http_monomial,
+      http_machine_state_digest: field_polynomial_digest(
+        &next_machine_state.flatten(),
+        ciphertext_digest,
+      ),
+      num_matches: step_in.num_matches - F::from(num_matched),
+      body_monomial,
+      json_monomial: F::ONE,
+      json_machine_state_digest: F::ZERO,
+      ..step_in
+    },
+    next_machine_state,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    circuits::plaintext_authentication::plaintext_authentication, digest::initial_digest, rstest,
+  };
+
+  const DATA_BYTES: usize = 512;
+  const MAX_STACK_HEIGHT: usize = 10;
+
+  fn pad(bytes: &[u8], data_bytes: usize) -> Vec<ByteOrPad> {
+    ByteOrPad::from_bytes_with_padding(bytes, data_bytes - bytes.len())
+  }
+
+  fn main_digests(ciphertext_digest: F) -> Vec<F> {
+    let mut main_digests = [RESPONSE_START_LINE, RESPONSE_HEADER_0, RESPONSE_HEADER_1]
+      .map(|line| polynomial_digest(line.as_bytes(), ciphertext_digest, 0))
+      .to_vec();
+    main_digests.resize(MAX_NUMBER_OF_HEADERS + 1, F::ZERO);
+    main_digests
+  }
+
+  /// Runs `PlaintextAuthentication` over every chunk, as the folds before `HTTPVerification` do
+  fn authenticated(chunks: &[&[u8]], data_bytes: usize) -> (F, PublicIo) {
+    let (_, ciphertext) = chunks.iter().fold((0, vec![]), |(offset, mut ciphertexts), chunk| {
+      ciphertexts.push(pad(&RESPONSE_CIPHERTEXT[offset..offset + chunk.len()], data_bytes));
+      (offset + chunk.len(), ciphertexts)
+    });
+    let (ciphertext_digest, mut step_in, _) = initial_digest::<MAX_STACK_HEIGHT>(
+      &mock_manifest(),
+      RESPONSE_PLAINTEXT.as_bytes(),
+      &ciphertext,
+    )
+    .unwrap();
+    for (chunk, ciphertext) in chunks.iter().zip(&ciphertext) {
+      step_in =
+        plaintext_authentication(step_in, &pad(chunk, data_bytes), ciphertext, ciphertext_digest)
+          .unwrap();
+    }
+    (ciphertext_digest, step_in)
+  }
+
+  #[test]
+  fn test_http_verification() {
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let (ciphertext_digest, step_in) = authenticated(&[plaintext], DATA_BYTES);
+
+    let (step_out, machine_state) = http_verification(
+      step_in,
+      RawHttpMachine::initial_state(),
+      &pad(plaintext, DATA_BYTES),
+      &main_digests(ciphertext_digest),
+      ciphertext_digest,
+    )
+    .unwrap();
+
+    let body = RESPONSE_BODY.as_bytes();
+    assert_eq!(step_out.accumulator, polynomial_digest(body, ciphertext_digest, 0));
+    assert_eq!(step_out.http_monomial, ciphertext_digest.pow([plaintext.len() as u64]));
+    assert_eq!(step_out.num_matches, F::ZERO);
+    assert_eq!(step_out.body_monomial, ciphertext_digest.pow([body.len() as u64 - 1]));
+    assert_eq!(machine_state, RawHttpMachine { parsing_body: F::ONE, ..Default::default() });
+    assert_eq!(
+      step_out.http_machine_state_digest,
+      field_polynomial_digest(&machine_state.flatten(), ciphertext_digest)
+    );
+  }
+
+  #[test]
+  fn test_http_verification_two_folds() {
+    // Split right where the body starts, as `circuits/test/full/full.test.ts` does
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let (head, body) = plaintext.split_at(plaintext.len() - RESPONSE_BODY.len());
+    let (ciphertext_digest, step_in) = authenticated(&[head, body], DATA_BYTES);
+    let main_digests = main_digests(ciphertext_digest);
+
+    let (step_out, machine_state) = http_verification(
+      step_in,
+      RawHttpMachine::initial_state(),
+      &pad(head, DATA_BYTES),
+      &main_digests,
+      ciphertext_digest,
+    )
+    .unwrap();
+    assert_eq!(step_out.num_matches, F::ZERO);
+    assert_eq!(step_out.body_monomial, F::ZERO);
+    assert_eq!(machine_state, RawHttpMachine { parsing_body: F::ONE, ..Default::default() });
+
+    let (step_out, _) = http_verification(
+      step_out,
+      machine_state,
+      &pad(body, DATA_BYTES),
+      &main_digests,
+      ciphertext_digest,
+    )
+    .unwrap();
+    assert_eq!(step_out.accumulator, polynomial_digest(body, ciphertext_digest, 0));
+    assert_eq!(step_out.body_monomial, ciphertext_digest.pow([body.len() as u64 - 1]));
+  }
+
+  /// Folds resume mid-line, starting inside the status code. The circuit drops the first byte of a
+  /// fold that starts a line, so no fold boundary is put right after a line break.
+  #[test]
+  fn test_http_verification_split_start_line() {
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let data_bytes = "HTTP/1.1 2".len();
+    let chunks = plaintext.chunks(data_bytes).collect::<Vec<_>>();
+    let (ciphertext_digest, mut step_out) = authenticated(&chunks, data_bytes);
+    let main_digests = main_digests(ciphertext_digest);
+
+    let mut machine_state = RawHttpMachine::initial_state();
+    for (idx, chunk) in chunks.iter().enumerate() {
+      (step_out, machine_state) = http_verification(
+        step_out,
+        machine_state,
+        &pad(chunk, data_bytes),
+        &main_digests,
+        ciphertext_digest,
+      )
+      .unwrap();
+      if idx == 0 {
+        assert_eq!(machine_state.parsing_start, F::from(2));
+        assert_eq!(machine_state.line_monomial, ciphertext_digest.pow([data_bytes as u64]));
+      }
+    }
+    assert_eq!(machine_state, RawHttpMachine { parsing_body: F::ONE, ..Default::default() });
+    assert_eq!(step_out.num_matches, F::ZERO);
+    assert_eq!(
+      step_out.accumulator,
+      polynomial_digest(RESPONSE_BODY.as_bytes(), ciphertext_digest, 0)
+    );
+  }
+
+  /// The circuit counts every space of the start line in `parsing_start`, past the three parts
+  #[test]
+  fn test_http_verification_reason_phrase_spaces() {
+    let plaintext = b"HTTP/1.1 404 Not Found\r\ncontent-length: 2\r\n\r\nhi";
+    let data_bytes = "HTTP/1.1 404 Not ".len();
+    let ciphertext_digest = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let main_digests = vec![F::ZERO; MAX_NUMBER_OF_HEADERS + 1];
+    let mut machine_state = RawHttpMachine::initial_state();
+    let mut step_out = PublicIo {
+      http_machine_state_digest: field_polynomial_digest(
+        &machine_state.flatten(),
+        ciphertext_digest,
+      ),
+      ..Default::default()
+    };
+
+    for (idx, chunk) in plaintext.chunks(data_bytes).enumerate() {
+      (step_out, machine_state) = http_verification(
+        step_out,
+        machine_state,
+        &pad(chunk, data_bytes),
+        &main_digests,
+        ciphertext_digest,
+      )
+      .unwrap();
+      if idx == 0 {
+        assert_eq!(machine_state.parsing_start, F::from(4));
+      }
+    }
+    assert_eq!(machine_state, RawHttpMachine { parsing_body: F::ONE, ..Default::default() });
+  }
+
+  #[rstest]
+  #[case::after_crlf(b"HTTP/1.1 200 OK\r\n", RawHttpMachine {
+    parsing_header: F::ONE,
+    parsing_field_name: F::ONE,
+    ..Default::default()
+  })]
+  #[case::after_cr(b"HTTP/1.1 200 OK\r", RawHttpMachine::default())]
+  #[case::in_line(b"HTTP/1.1 200 OK", RawHttpMachine { parsing_start: F::from(3), ..Default::default() })]
+  fn test_http_verification_padding(#[case] plaintext: &[u8], #[case] expected: RawHttpMachine) {
+    let ciphertext_digest = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let step_in = PublicIo {
+      http_machine_state_digest: field_polynomial_digest(
+        &RawHttpMachine::initial_state().flatten(),
+        ciphertext_digest,
+      ),
+      ..Default::default()
+    };
+    let (_, machine_state) = http_verification(
+      step_in,
+      RawHttpMachine::initial_state(),
+      &pad(plaintext, 32),
+      &[F::ZERO; MAX_NUMBER_OF_HEADERS + 1],
+      ciphertext_digest,
+    )
+    .unwrap();
+    // Only the status flags, the line digest has the padding in it
+    assert_eq!(machine_state.flatten()[..6], expected.flatten()[..6]);
+  }
+
+  #[test]
+  fn test_http_verification_wrong_lock() {
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let (ciphertext_digest, step_in) = authenticated(&[plaintext], DATA_BYTES);
+    let mut main_digests = main_digests(ciphertext_digest);
+    main_digests[1] = F::ZERO;
+
+    assert!(matches!(
+      http_verification(
+        step_in,
+        RawHttpMachine::initial_state(),
+        &pad(plaintext, DATA_BYTES),
+        &main_digests,
+        ciphertext_digest,
+      ),
+      Err(WitnessGeneratorError::CircuitConstraint(_))
+    ));
+  }
+
+  #[test]
+  fn test_http_verification_invalid_machine_state() {
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let (ciphertext_digest, step_in) = authenticated(&[plaintext], DATA_BYTES);
+    let machine_state = RawHttpMachine { parsing_body: F::ONE, ..RawHttpMachine::initial_state() };
+    let step_in = PublicIo {
+      http_machine_state_digest: field_polynomial_digest(
+        &machine_state.flatten(),
+        ciphertext_digest,
+      ),
+      ..step_in
+    };
+
+    assert!(matches!(
+      http_verification(
+        step_in,
+        machine_state,
+        &pad(plaintext, DATA_BYTES),
+        &main_digests(ciphertext_digest),
+        ciphertext_digest,
+      ),
+      Err(WitnessGeneratorError::CircuitConstraint(_))
+    ));
+  }
+}
@@ -0,0 +1,244 @@
+use super::*;
+use crate::json::{parser::parse_raw_from, RawJsonMachine};
+
+/// Computes the `step_out` of `JSONExtraction` for one chunk of the HTTP body.
+///
+/// Parsing resumes from the [`RawJsonMachine`] state the circuit takes, which only holds digests
+/// of the labels, so a fold can be replayed from its `state` input, see
+/// [`RawJsonMachine::unflatten`]. Labels are digested with `ciphertext_digest`, which replaces the
+/// polynomial input of `machine`.
+///
+/// # Arguments
+/// - `step_in`: public IO of the fold
+/// - `machine`: JSON parser state at the start of `data`, whose digest is `step_in[8]`
+/// - `data`: the body chunk, padded with [`ByteOrPad::Pad`] to `DATA_BYTES`
+/// - `sequence_digest`: compressed tree hash of the key sequence, see
+///   [`RawJsonMachine::compress_tree_hash`]
+/// - `value_digest`: polynomial digest of the value to extract
+/// - `ciphertext_digest`: digest of all ciphertext chunks
+/// # Returns
+/// - the `step_out` of the fold
+/// - the parser state at the end of `data`, the `state` of the next fold
+///
+/// Padding is expected only after the JSON, it leaves the parser state unchanged.
+pub fn json_extraction<const MAX_STACK_HEIGHT: usize>(
+  step_in: PublicIo,
+  machine: RawJsonMachine<MAX_STACK_HEIGHT>,
+  data: &[ByteOrPad],
+  sequence_digest: F,
+  value_digest: F,
+  ciphertext_digest: F,
+) -> Result<(PublicIo, RawJsonMachine<MAX_STACK_HEIGHT>), WitnessGeneratorError>
+where
+  [(); MAX_STACK_HEIGHT * 4 + 4]:,
+{
+  let machine = RawJsonMachine { polynomial_input: ciphertext_digest, ..machine };
+  if step_in.json_machine_state_digest
+    != field_polynomial_digest(&machine.flatten(), ciphertext_digest)
+  {
+    return Err(WitnessGeneratorError::CircuitConstraint(
+      "step_in[8] is not the digest of the machine state".to_string(),
+    ));
+  }
+  if step_in.json_sequence_digest_hashed != poseidon::<1>(&[sequence_digest]) {
+    return Err(WitnessGeneratorError::CircuitConstraint(
+      "step_in[9] is not the hash of the sequence digest".to_string(),
+    ));
+  }
+
+  let bytes = data
+    .iter()
+    .filter_map(|byte| match byte {
+      ByteOrPad::Byte(byte) => Some(*byte),
+      ByteOrPad::Pad => None,
+    })
+    .collect::<Vec<_>>();
+  let parsed = parse_raw_from(&bytes, machine.clone()).map_err(|err| match err {
+    WitnessGeneratorError::InvalidJsonMachine(reason) => WitnessGeneratorError::CircuitConstraint(
+      format!("machine state is not a parser state: {reason}"),
+    ),
+    err => err,
+  })?;
+  let mut parsed = parsed.into_iter();
+
+  // A byte matches when the key sequence is on the stack and the value is in the tree hash
+  let mut state = machine;
+  let mut total_matches = 0;
+  for byte in data {
+    if let (ByteOrPad::Byte(_), Some(next)) = (byte, parsed.next()) {
+      state = next;
+    }
+    if state.compress_tree_hash() == sequence_digest
+      && state.tree_hash.iter().map(|(_, value)| *value).sum::<F>() == value_digest
+    {
+      total_matches += 1;
+    }
+  }
+
+  let (data_digest, json_monomial) =
+    data_digest_with_counter(data, ciphertext_digest, step_in.json_monomial);
+  let accumulator = step_in.accumulator - data_digest + value_digest * F::from(total_matches);
+  let json_machine_state_digest = field_polynomial_digest(&state.flatten(), ciphertext_digest);
+
+  if value_digest != F::ZERO
+    && (json_machine_state_digest == F::ZERO) != (accumulator == value_digest)
+  {
+    return Err(WitnessGeneratorError::CircuitConstraint(
+      "value must be extracted exactly when the JSON is fully parsed".to_string(),
+    ));
+  }
+  if step_in.plaintext_monomial != step_in.http_monomial {
+    return Err(WitnessGeneratorError::CircuitConstraint(
+      "plaintext and HTTP circuits digested a different number of bytes".to_string(),
+    ));
+  }
+
+  Ok((PublicIo { accumulator, json_monomial, json_machine_state_digest, ..step_in }, state))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    circuits::{
+      http_verification::http_verification, plaintext_authentication::plaintext_authentication,
+    },
+    digest::initial_digest,
+    http::RawHttpMachine,
+  };
+
+  const DATA_BYTES: usize = 512;
+  const MAX_STACK_HEIGHT: usize = 10;
+
+  fn pad(bytes: &[u8]) -> Vec<ByteOrPad> {
+    ByteOrPad::from_bytes_with_padding(bytes, DATA_BYTES - bytes.len())
+  }
+
+  /// Runs `PlaintextAuthentication` and `HTTPVerification` over the mock response
+  fn verified() -> (F, PublicIo) {
+    let (ciphertext_digest, step_in, _) =
+      initial_digest::<MAX_STACK_HEIGHT>(&mock_manifest(), RESPONSE_PLAINTEXT.as_bytes(), &[pad(
+        &RESPONSE_CIPHERTEXT,
+      )])
+      .unwrap();
+    let plaintext = pad(RESPONSE_PLAINTEXT.as_bytes());
+    let step_out =
+      plaintext_authentication(step_in, &plaintext, &pad(&RESPONSE_CIPHERTEXT), ciphertext_digest)
+        .unwrap();
+
+    let mut main_digests = [RESPONSE_START_LINE, RESPONSE_HEADER_0, RESPONSE_HEADER_1]
+      .map(|line| polynomial_digest(line.as_bytes(), ciphertext_digest, 0))
+      .to_vec();
+    main_digests.resize(MAX_NUMBER_OF_HEADERS + 1, F::ZERO);
+    let (step_out, _) = http_verification(
+      step_out,
+      RawHttpMachine::initial_state(),
+      &plaintext,
+      &main_digests,
+      ciphertext_digest,
+    )
+    .unwrap();
+    (ciphertext_digest, step_out)
+  }
+
+  fn sequence_digest(ciphertext_digest: F) -> F {
+    RawJsonMachine::<MAX_STACK_HEIGHT>::from_chosen_sequence_and_input(
+      ciphertext_digest,
+      &mock_manifest().response.body.json,
+    )
+    .unwrap()
+    .compress_tree_hash()
+  }
+
+  #[test]
+  fn test_json_extraction() {
+    let (ciphertext_digest, step_in) = verified();
+    let value_digest = polynomial_digest(b"Taylor Swift", ciphertext_digest, 0);
+
+    let (step_out, machine) = json_extraction::<MAX_STACK_HEIGHT>(
+      step_in,
+      RawJsonMachine::initial_state(),
+      &pad(RESPONSE_BODY.as_bytes()),
+      sequence_digest(ciphertext_digest),
+      value_digest,
+      ciphertext_digest,
+    )
+    .unwrap();
+
+    assert_eq!(step_out.accumulator, value_digest);
+    assert_eq!(step_out.json_monomial, ciphertext_digest.pow([RESPONSE_BODY.len() as u64]));
+    assert_eq!(step_out.json_machine_state_digest, F::ZERO);
+    assert_eq!(step_out.json_sequence_digest_hashed, step_in.json_sequence_digest_hashed);
+    assert_eq!(machine.flatten(), RawJsonMachine::<MAX_STACK_HEIGHT>::initial_state().flatten());
+  }
+
+  #[test]
+  fn test_json_extraction_two_folds() {
+    let (ciphertext_digest, step_in) = verified();
+    let value_digest = polynomial_digest(b"Taylor Swift", ciphertext_digest, 0);
+    let sequence_digest = sequence_digest(ciphertext_digest);
+    let (first, second) = RESPONSE_BODY.as_bytes().split_at(RESPONSE_BODY.len() / 2);
+
+    let (step_out, machine) = json_extraction::<MAX_STACK_HEIGHT>(
+      step_in,
+      RawJsonMachine::initial_state(),
+      &pad(first),
+      sequence_digest,
+      value_digest,
+      ciphertext_digest,
+    )
+    .unwrap();
+    assert_ne!(step_out.json_machine_state_digest, F::ZERO);
+
+    // Replay the second fold from its `state` input
+    let state = machine.flatten();
+    let (step_out, _) = json_extraction::<MAX_STACK_HEIGHT>(
+      step_out,
+      RawJsonMachine::unflatten(F::ZERO, &state),
+      &pad(second),
+      sequence_digest,
+      value_digest,
+      ciphertext_digest,
+    )
+    .unwrap();
+    assert_eq!(step_out.accumulator, value_digest);
+    assert_eq!(step_out.json_machine_state_digest, F::ZERO);
+  }
+
+  #[test]
+  fn test_json_extraction_wrong_value() {
+    let (ciphertext_digest, step_in) = verified();
+    assert!(matches!(
+      json_extraction::<MAX_STACK_HEIGHT>(
+        step_in,
+        RawJsonMachine::initial_state(),
+        &pad(RESPONSE_BODY.as_bytes()),
+        sequence_digest(ciphertext_digest),
+        polynomial_digest(b"Tayler Swift", ciphertext_digest, 0),
+        ciphertext_digest,
+      ),
+      Err(WitnessGeneratorError::CircuitConstraint(_))
+    ));
+  }
+
+  #[test]
+  fn test_json_extraction_invalid_state() {
+    let (ciphertext_digest, mut step_in) = verified();
+    let mut machine = RawJsonMachine::<MAX_STACK_HEIGHT>::initial_state();
+    machine.stack[0] = (F::from(3), F::ZERO);
+    step_in.json_machine_state_digest =
+      field_polynomial_digest(&machine.flatten(), ciphertext_digest);
+
+    assert!(matches!(
+      json_extraction::<MAX_STACK_HEIGHT>(
+        step_in,
+        machine,
+        &pad(RESPONSE_BODY.as_bytes()),
+        sequence_digest(ciphertext_digest),
+        polynomial_digest(b"Taylor Swift", ciphertext_digest, 0),
+        ciphertext_digest,
+      ),
+      Err(WitnessGeneratorError::CircuitConstraint(_))
+    ));
+  }
+}
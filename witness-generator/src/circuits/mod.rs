@@ -0,0 +1,29 @@
+//! Off-circuit reference models of the NIVC circuits.
+//!
+//! Each model computes the `step_out` its circuit produces for a given `step_in` and private
+//! inputs, and returns [`WitnessGeneratorError::CircuitConstraint`] where the circuit would fail
+//! to satisfy a constraint. This lets witnesses be checked before folding.
+
+use super::*;
+use crate::public_io::PublicIo;
+
+pub mod http_verification;
+pub mod json_extraction;
+pub mod plaintext_authentication;
+
+/// `PolynomialDigestWithCounter` of the zeroed data starting at `monomial`, together with
+/// `monomial` advanced once per non-padding byte, as every circuit carries it to the next fold.
+fn data_digest_with_counter(data: &[ByteOrPad], polynomial_input: F, monomial: F) -> (F, F) {
+  // The digest monomial advances at every byte, padding is zeroed out
+  let mut digest_monomial = monomial;
+  let mut digest = F::ZERO;
+  let mut next_monomial = monomial;
+  for byte in data {
+    if let ByteOrPad::Byte(byte) = byte {
+      digest += F::from(u64::from(*byte)) * digest_monomial;
+      next_monomial *= polynomial_input;
+    }
+    digest_monomial *= polynomial_input;
+  }
+  (digest, next_monomial)
+}
@@ -0,0 +1,141 @@
+use super::*;
+use crate::http::RawHttpMachine;
+
+/// Computes the `step_out` of `PlaintextAuthentication` for one plaintext chunk.
+///
+/// # Arguments
+/// - `step_in`: public IO of the fold
+/// - `plaintext`: the plaintext chunk, padded with [`ByteOrPad::Pad`] to `DATA_BYTES`
+/// - `ciphertext`: the encryption of `plaintext`, padded at the same positions
+/// - `ciphertext_digest`: digest of all ciphertext chunks, see
+///   [`ciphertext_digest`](crate::digest::ciphertext_digest)
+pub fn plaintext_authentication(
+  step_in: PublicIo,
+  plaintext: &[ByteOrPad],
+  ciphertext: &[ByteOrPad],
+  ciphertext_digest: F,
+) -> Result<PublicIo, WitnessGeneratorError> {
+  if plaintext.len() != ciphertext.len() {
+    return Err(WitnessGeneratorError::CircuitConstraint(format!(
+      "plaintext has {} bytes but ciphertext has {}",
+      plaintext.len(),
+      ciphertext.len()
+    )));
+  }
+  // The circuit encrypts the plaintext and pads the ciphertext wherever the plaintext is padded
+  if let Some(idx) = plaintext
+    .iter()
+    .zip(ciphertext)
+    .position(|(pt, ct)| matches!(pt, ByteOrPad::Pad) != matches!(ct, ByteOrPad::Pad))
+  {
+    return Err(WitnessGeneratorError::CircuitConstraint(format!(
+      "plaintext and ciphertext padding differ at byte {idx}"
+    )));
+  }
+
+  let (plaintext_digest, plaintext_monomial) =
+    data_digest_with_counter(plaintext, ciphertext_digest, step_in.plaintext_monomial);
+
+  let part_ciphertext_digest = data_hasher(ciphertext, step_in.ciphertext_digest_seed);
+
+  Ok(PublicIo {
+    accumulator: step_in.accumulator + step_in.ciphertext_digest_seed - part_ciphertext_digest
+      + plaintext_digest,
+    plaintext_monomial,
+    http_machine_state_digest: field_polynomial_digest(
+      &RawHttpMachine::initial_state().flatten(),
+      ciphertext_digest,
+    ),
+    body_monomial: F::ZERO,
+    ciphertext_digest_seed: part_ciphertext_digest,
+    ..step_in
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::digest::{ciphertext_digest, initial_digest};
+
+  const DATA_BYTES: usize = 1024;
+  const MAX_STACK_HEIGHT: usize = 10;
+
+  fn pad(bytes: &[u8], data_bytes: usize) -> Vec<ByteOrPad> {
+    ByteOrPad::from_bytes_with_padding(bytes, data_bytes - bytes.len())
+  }
+
+  #[test]
+  fn test_plaintext_authentication() {
+    let ciphertext = pad(&RESPONSE_CIPHERTEXT, DATA_BYTES);
+    let (ciphertext_digest, step_in, _) = initial_digest::<MAX_STACK_HEIGHT>(
+      &mock_manifest(),
+      RESPONSE_PLAINTEXT.as_bytes(),
+      std::slice::from_ref(&ciphertext),
+    )
+    .unwrap();
+
+    let step_out = plaintext_authentication(
+      step_in,
+      &pad(RESPONSE_PLAINTEXT.as_bytes(), DATA_BYTES),
+      &ciphertext,
+      ciphertext_digest,
+    )
+    .unwrap();
+
+    // The ciphertext digest cancels out, leaving only the plaintext digest
+    assert_eq!(
+      step_out.accumulator,
+      polynomial_digest(RESPONSE_PLAINTEXT.as_bytes(), ciphertext_digest, 0)
+    );
+    assert_eq!(
+      step_out.plaintext_monomial,
+      ciphertext_digest.pow([RESPONSE_PLAINTEXT.len() as u64])
+    );
+    assert_eq!(step_out.http_machine_state_digest, F::ONE + ciphertext_digest.pow([7]));
+    assert_eq!(step_out.ciphertext_digest_seed, ciphertext_digest);
+    assert_eq!(step_out.header_verification_lock, step_in.header_verification_lock);
+    assert_eq!(step_out.json_sequence_digest_hashed, step_in.json_sequence_digest_hashed);
+  }
+
+  #[test]
+  fn test_plaintext_authentication_chains_folds() {
+    const DATA_BYTES: usize = 256;
+    let chunks =
+      RESPONSE_PLAINTEXT.as_bytes().chunks(DATA_BYTES).zip(RESPONSE_CIPHERTEXT.chunks(DATA_BYTES));
+    let ciphertexts =
+      chunks.clone().map(|(_, ciphertext)| pad(ciphertext, DATA_BYTES)).collect::<Vec<_>>();
+    let ciphertext_digest = ciphertext_digest(&ciphertexts);
+
+    let mut step_in =
+      PublicIo { accumulator: ciphertext_digest, plaintext_monomial: F::ONE, ..Default::default() };
+    for ((plaintext, _), ciphertext) in chunks.zip(&ciphertexts) {
+      step_in = plaintext_authentication(
+        step_in,
+        &pad(plaintext, DATA_BYTES),
+        ciphertext,
+        ciphertext_digest,
+      )
+      .unwrap();
+    }
+    assert_eq!(
+      step_in.accumulator,
+      polynomial_digest(RESPONSE_PLAINTEXT.as_bytes(), ciphertext_digest, 0)
+    );
+    assert_eq!(step_in.ciphertext_digest_seed, ciphertext_digest);
+  }
+
+  #[test]
+  fn test_plaintext_authentication_padding_mismatch() {
+    let mut plaintext = pad(RESPONSE_PLAINTEXT.as_bytes(), DATA_BYTES);
+    plaintext[RESPONSE_PLAINTEXT.len()] = ByteOrPad::Byte(0);
+    assert!(matches!(
+      plaintext_authentication(
+        PublicIo::default(),
+        &plaintext,
+        &pad(&RESPONSE_CIPHERTEXT, DATA_BYTES),
+        F::ONE
+      ),
+      Err(WitnessGeneratorError::CircuitConstraint(_))
+    ));
+  }
+}
@@ -0,0 +1,340 @@
+use super::*;
+use crate::{
+  http::{plaintext_header_lines, RawHttpMachine},
+  json::RawJsonMachine,
+  manifest::Manifest,
+  public_io::PublicIo,
+};
+
+/// Chains [`data_hasher`] over each ciphertext chunk, seeding every chunk with the digest of the
+/// chunks before it.
+pub fn ciphertext_digest(ciphertexts: &[Vec<ByteOrPad>]) -> F { chained_data_hasher(ciphertexts) }
+
+fn chained_data_hasher<'a>(ciphertexts: impl IntoIterator<Item = &'a Vec<ByteOrPad>>) -> F {
+  ciphertexts.into_iter().fold(F::ZERO, |seed, ciphertext| data_hasher(ciphertext, seed))
+}
+
+/// Digests each line (start line or `name: value` header) with the ciphertext digest as polynomial
+/// input.
+fn line_digests(lines: impl IntoIterator<Item = Vec<u8>>, polynomial_input: F) -> Vec<F> {
+  lines.into_iter().map(|line| polynomial_digest(&line, polynomial_input, 0)).collect()
+}
+
+/// Sum of the hashed line digests, checked against `main_digests` by `HTTPVerification`.
+fn header_verification_lock(digests: &[F]) -> F {
+  digests.iter().map(|digest| poseidon::<1>(&[*digest])).sum()
+}
+
+/// Builds the `main_digests` input of `HTTPVerification` for a single HTTP message.
+///
+/// # Arguments
+/// - `start_line`: the start line, without the trailing CRLF
+/// - `headers`: the header lines to lock, see [`plaintext_header_lines`]
+/// - `polynomial_input`: the ciphertext digest
+/// # Returns
+/// - the digests of the start line and headers, zero-padded to `MAX_NUMBER_OF_HEADERS + 1`
+/// - the header verification lock, the `step_in[4]` these digests are checked against
+pub fn main_digests(
+  start_line: Vec<u8>,
+  headers: impl IntoIterator<Item = Vec<u8>>,
+  polynomial_input: F,
+) -> Result<([F; MAX_NUMBER_OF_HEADERS + 1], F), WitnessGeneratorError> {
+  let digests = line_digests(std::iter::once(start_line).chain(headers), polynomial_input);
+  if digests.len() > MAX_NUMBER_OF_HEADERS + 1 {
+    return Err(WitnessGeneratorError::InvalidManifest(format!(
+      "{} headers exceed the max number of headers {MAX_NUMBER_OF_HEADERS}",
+      digests.len() - 1
+    )));
+  }
+
+  let mut main_digests = [F::ZERO; MAX_NUMBER_OF_HEADERS + 1];
+  main_digests[..digests.len()].copy_from_slice(&digests);
+  Ok((main_digests, header_verification_lock(&digests)))
+}
+
+/// Hashes the digest of the JSON key sequence with the ciphertext digest as polynomial input.
+fn json_sequence_digest_hashed<const MAX_STACK_HEIGHT: usize>(
+  manifest: &Manifest,
+  polynomial_input: F,
+) -> Result<F, WitnessGeneratorError> {
+  let sequence = RawJsonMachine::<MAX_STACK_HEIGHT>::from_chosen_sequence_and_input(
+    polynomial_input,
+    &manifest.response.body.json,
+  )?;
+  Ok(poseidon::<1>(&[sequence.compress_tree_hash()]))
+}
+
+/// Computes the initial public IO of the NIVC program for a response-only proof.
+///
+/// # Arguments
+/// - `manifest`: the statements about the response to prove
+/// - `plaintext`: the response plaintext, the lines of the manifest headers are locked exactly as
+///   it has them, see [`plaintext_header_lines`]
+/// - `ciphertexts`: the response ciphertext chunks, each padded with [`ByteOrPad::Pad`] exactly as
+///   they are given to `PlaintextAuthentication`
+/// # Returns
+/// - the ciphertext digest, used as the polynomial input of every digest in the program
+/// - the `step_in` of the first fold
+/// - the `main_digests` of `HTTPVerification`, which `step_in[4]` locks
+pub fn initial_digest<const MAX_STACK_HEIGHT: usize>(
+  manifest: &Manifest,
+  plaintext: &[u8],
+  ciphertexts: &[Vec<ByteOrPad>],
+) -> Result<(F, PublicIo, [F; MAX_NUMBER_OF_HEADERS + 1]), WitnessGeneratorError> {
+  let ciphertext_digest = ciphertext_digest(ciphertexts);
+  let header_lines = plaintext_header_lines(plaintext, &manifest.response.headers)?;
+
+  let num_matches = 1 + header_lines.len() as u64;
+  let (main_digests, header_verification_lock) =
+    main_digests(manifest.response.start_line(), header_lines, ciphertext_digest)?;
+  let json_sequence_digest_hashed =
+    json_sequence_digest_hashed::<MAX_STACK_HEIGHT>(manifest, ciphertext_digest)?;
+
+  Ok((
+    ciphertext_digest,
+    PublicIo {
+      accumulator: ciphertext_digest,
+      plaintext_monomial: F::ONE,
+      http_monomial: F::ONE,
+      http_machine_state_digest: F::ONE,
+      header_verification_lock,
+      num_matches: F::from(num_matches),
+      body_monomial: F::ZERO,
+      json_monomial: F::ONE,
+      json_machine_state_digest: F::ZERO,
+      json_sequence_digest_hashed,
+      ciphertext_digest_seed: F::ZERO,
+    },
+    main_digests,
+  ))
+}
+
+/// Computes the initial public IO of the NIVC program for a proof over both the request and the
+/// response. The manifest is checked with [`Manifest::validate_combined`] first.
+///
+/// # Arguments
+/// - `manifest`: the statements about the request and response to prove
+/// - `request_plaintext`, `response_plaintext`: the plaintexts, whose header lines are locked as in
+///   [`initial_digest`]
+/// - `request_ciphertexts`: the request ciphertext chunks, padded as in [`initial_digest`]
+/// - `response_ciphertexts`: the response ciphertext chunks, padded as in [`initial_digest`]
+/// # Returns
+/// - the ciphertext digest over the request chunks followed by the response chunks
+/// - the `step_in` of the first fold, starting from the digest of the initial HTTP machine state
+/// - the unpadded `main_digests` of `HTTPVerification`, ordered request start line, response start
+///   line, request headers, response headers
+pub fn combined_initial_digest<const MAX_STACK_HEIGHT: usize>(
+  manifest: &Manifest,
+  request_plaintext: &[u8],
+  response_plaintext: &[u8],
+  request_ciphertexts: &[Vec<ByteOrPad>],
+  response_ciphertexts: &[Vec<ByteOrPad>],
+) -> Result<(F, PublicIo, Vec<F>), WitnessGeneratorError> {
+  manifest.validate_combined()?;
+  let ciphertext_digest =
+    chained_data_hasher(request_ciphertexts.iter().chain(response_ciphertexts));
+
+  let main_digests = line_digests(
+    [manifest.request.start_line(), manifest.response.start_line()]
+      .into_iter()
+      .chain(plaintext_header_lines(request_plaintext, &manifest.request.headers)?)
+      .chain(plaintext_header_lines(response_plaintext, &manifest.response.headers)?),
+    ciphertext_digest,
+  );
+
+  let header_verification_lock = header_verification_lock(&main_digests);
+  let initial_http_machine_state_digest =
+    field_polynomial_digest(&RawHttpMachine::initial_state().flatten(), ciphertext_digest);
+  let num_matches = main_digests.len() as u64;
+  let json_sequence_digest_hashed =
+    json_sequence_digest_hashed::<MAX_STACK_HEIGHT>(manifest, ciphertext_digest)?;
+
+  Ok((
+    ciphertext_digest,
+    PublicIo {
+      accumulator: ciphertext_digest,
+      plaintext_monomial: F::ONE,
+      http_monomial: F::ONE,
+      http_machine_state_digest: initial_http_machine_state_digest,
+      header_verification_lock,
+      num_matches: F::from(num_matches),
+      body_monomial: F::ZERO,
+      json_monomial: F::ONE,
+      json_machine_state_digest: F::ZERO,
+      json_sequence_digest_hashed,
+      ciphertext_digest_seed: F::ZERO,
+    },
+    main_digests,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{http::Headers, json::JsonKey};
+
+  const DATA_BYTES: usize = 1024;
+  const MAX_STACK_HEIGHT: usize = 10;
+
+  // Same value as `check_ciphertext_digest` in `circuits/test/full/full.test.ts`
+  const CHECK_CIPHERTEXT_DIGEST: &str =
+    "5947802862726868637928743536818722886587721698845887498686185738472802646104";
+
+  const REQUEST_PLAINTEXT: &[u8] = b"GET / HTTP/1.1\r\n\r\n";
+
+  fn padded_ciphertext() -> Vec<ByteOrPad> {
+    ByteOrPad::from_bytes_with_padding(&RESPONSE_CIPHERTEXT, DATA_BYTES - RESPONSE_CIPHERTEXT.len())
+  }
+
+  #[test]
+  fn test_initial_digest() {
+    let manifest = mock_manifest();
+    let (ciphertext_digest, step_in, main_digests) =
+      initial_digest::<MAX_STACK_HEIGHT>(&manifest, RESPONSE_PLAINTEXT.as_bytes(), &[
+        padded_ciphertext(),
+      ])
+      .unwrap();
+    assert_eq!(ciphertext_digest, F::from_str_vartime(CHECK_CIPHERTEXT_DIGEST).unwrap());
+    assert_eq!(step_in.accumulator, ciphertext_digest);
+
+    let lock = [RESPONSE_START_LINE, RESPONSE_HEADER_0, RESPONSE_HEADER_1]
+      .iter()
+      .map(|line| poseidon::<1>(&[polynomial_digest(line.as_bytes(), ciphertext_digest, 0)]))
+      .sum::<F>();
+    assert_eq!(step_in.header_verification_lock, lock);
+    assert_eq!(header_verification_lock(&main_digests[..3]), lock);
+    assert_eq!(step_in.num_matches, F::from(3));
+
+    let sequence = RawJsonMachine::<MAX_STACK_HEIGHT>::from_chosen_sequence_and_input(
+      ciphertext_digest,
+      &mock_manifest().response.body.json,
+    )
+    .unwrap();
+    assert_eq!(
+      step_in.json_sequence_digest_hashed,
+      poseidon::<1>(&[sequence.compress_tree_hash()])
+    );
+    assert_eq!(step_in.ciphertext_digest_seed, F::ZERO);
+  }
+
+  #[test]
+  fn test_ciphertext_digest_chains_chunks() {
+    let (first, second) = RESPONSE_CIPHERTEXT.split_at(RESPONSE_CIPHERTEXT.len() / 2);
+    let chunks = [first, second]
+      .map(|chunk| ByteOrPad::from_bytes_with_padding(chunk, DATA_BYTES - chunk.len()));
+    assert_eq!(
+      ciphertext_digest(&chunks),
+      data_hasher(&chunks[1], data_hasher(&chunks[0], F::ZERO))
+    );
+  }
+
+  #[test]
+  fn test_initial_digest_sequence_too_long() {
+    let mut manifest = mock_manifest();
+    manifest.response.body.json = (0..MAX_STACK_HEIGHT + 1).map(JsonKey::Num).collect();
+    assert!(initial_digest::<MAX_STACK_HEIGHT>(&manifest, RESPONSE_PLAINTEXT.as_bytes(), &[
+      padded_ciphertext()
+    ])
+    .is_err());
+  }
+
+  /// The lock covers the header lines as the plaintext has them, not as the manifest spells them
+  #[test]
+  fn test_initial_digest_locks_plaintext_header_lines() {
+    let mut manifest = mock_manifest();
+    manifest.response.headers = Headers::from([("foo", "bar")]);
+    let plaintext = b"HTTP/1.1 200 OK\r\nfoo:bar\r\n\r\n{}";
+    let (ciphertext_digest, step_in, main_digests) =
+      initial_digest::<MAX_STACK_HEIGHT>(&manifest, plaintext, &[padded_ciphertext()]).unwrap();
+    assert_eq!(main_digests[1], polynomial_digest(b"foo:bar", ciphertext_digest, 0));
+    assert_eq!(step_in.header_verification_lock, header_verification_lock(&main_digests[..2]));
+  }
+
+  #[test]
+  fn test_initial_digest_header_not_in_plaintext() {
+    let mut manifest = mock_manifest();
+    manifest.response.headers.append("x-missing", "1");
+    assert!(matches!(
+      initial_digest::<MAX_STACK_HEIGHT>(&manifest, RESPONSE_PLAINTEXT.as_bytes(), &[
+        padded_ciphertext()
+      ]),
+      Err(WitnessGeneratorError::InvalidManifest(_))
+    ));
+  }
+
+  #[test]
+  fn test_main_digests() {
+    let ciphertext_digest = F::from(7);
+    let headers = [RESPONSE_HEADER_0, RESPONSE_HEADER_1].map(|line| line.as_bytes().to_vec());
+    let (digests, lock) =
+      main_digests(RESPONSE_START_LINE.as_bytes().to_vec(), headers, ciphertext_digest).unwrap();
+
+    let expected = [RESPONSE_START_LINE, RESPONSE_HEADER_0, RESPONSE_HEADER_1]
+      .map(|line| polynomial_digest(line.as_bytes(), ciphertext_digest, 0));
+    assert_eq!(digests[..3], expected);
+    assert!(digests[3..].iter().all(|digest| *digest == F::ZERO));
+    assert_eq!(lock, expected.iter().map(|digest| poseidon::<1>(&[*digest])).sum::<F>());
+
+    let headers = (0..=MAX_NUMBER_OF_HEADERS).map(|idx| format!("x-header-{idx}: ").into_bytes());
+    assert!(matches!(
+      main_digests(RESPONSE_START_LINE.as_bytes().to_vec(), headers, ciphertext_digest),
+      Err(WitnessGeneratorError::InvalidManifest(_))
+    ));
+  }
+
+  #[test]
+  fn test_combined_initial_digest() {
+    let (request, response) = RESPONSE_CIPHERTEXT.split_at(64);
+    let request = [ByteOrPad::from_bytes_with_padding(request, DATA_BYTES - request.len())];
+    let response = [ByteOrPad::from_bytes_with_padding(response, DATA_BYTES - response.len())];
+    let manifest = mock_manifest();
+
+    let (ciphertext_digest, step_in, main_digests) = combined_initial_digest::<MAX_STACK_HEIGHT>(
+      &manifest,
+      REQUEST_PLAINTEXT,
+      RESPONSE_PLAINTEXT.as_bytes(),
+      &request,
+      &response,
+    )
+    .unwrap();
+    assert_eq!(ciphertext_digest, data_hasher(&response[0], data_hasher(&request[0], F::ZERO)));
+    assert_eq!(step_in.accumulator, ciphertext_digest);
+    assert_eq!(step_in.http_machine_state_digest, F::ONE + ciphertext_digest.pow_vartime([7]));
+
+    // The mock request has no headers
+    let expected_digests =
+      ["GET / HTTP/1.1", RESPONSE_START_LINE, RESPONSE_HEADER_0, RESPONSE_HEADER_1]
+        .map(|line| polynomial_digest(line.as_bytes(), ciphertext_digest, 0));
+    assert_eq!(main_digests, expected_digests);
+    assert_eq!(
+      step_in.header_verification_lock,
+      main_digests.iter().map(|digest| poseidon::<1>(&[*digest])).sum::<F>()
+    );
+    assert_eq!(step_in.num_matches, F::from(4));
+    assert_eq!(
+      step_in.json_sequence_digest_hashed,
+      json_sequence_digest_hashed::<MAX_STACK_HEIGHT>(&manifest, ciphertext_digest).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_combined_initial_digest_too_many_headers() {
+    let mut manifest = mock_manifest();
+    manifest.request.headers =
+      (0..MAX_NUMBER_OF_HEADERS).map(|idx| (format!("x-header-{idx}"), String::new())).collect();
+    let request = format!(
+      "GET / HTTP/1.1\r\n{}\r\n",
+      (0..MAX_NUMBER_OF_HEADERS).map(|idx| format!("x-header-{idx}:\r\n")).collect::<String>()
+    );
+    assert!(matches!(
+      combined_initial_digest::<MAX_STACK_HEIGHT>(
+        &manifest,
+        request.as_bytes(),
+        RESPONSE_PLAINTEXT.as_bytes(),
+        &[],
+        &[padded_ciphertext()]
+      ),
+      Err(WitnessGeneratorError::InvalidManifest(_))
+    ));
+  }
+}
@@ -1,13 +1,35 @@
 use thiserror::Error;
 
+use crate::http::{message::HttpMessageError, HttpStatus};
+
 #[derive(Error, Debug)]
 pub enum WitnessGeneratorError {
   #[error("{0}")]
   CatchAll(String),
   #[error(transparent)]
   SerdeJson(#[from] serde_json::Error),
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
   #[error("{0}")]
   JsonParser(String),
   #[error("json key not found: {0}")]
   JsonKeyError(String),
+  #[error("invalid manifest: {0}")]
+  InvalidManifest(String),
+  #[error(transparent)]
+  HttpMessage(#[from] HttpMessageError),
+  #[error("malformed http at byte {offset}, parser status {status:?}")]
+  HttpParser { offset: usize, status: HttpStatus },
+  #[error("invalid http machine state: {0}")]
+  InvalidHttpMachine(String),
+  #[error("invalid json machine state: {0}")]
+  InvalidJsonMachine(String),
+  #[error("circuit constraint not satisfied: {0}")]
+  CircuitConstraint(String),
+  #[error(
+    "fold at byte {offset} starts a header line, HTTPVerification would drop its first byte"
+  )]
+  HeaderLineAtFoldBoundary { offset: usize },
+  #[error("rom of length {length} exceeds max rom length {max_rom_length}")]
+  RomTooLong { length: usize, max_rom_length: usize },
 }
@@ -0,0 +1,148 @@
+//! Decoding of `Transfer-Encoding: chunked` bodies, keeping where each byte is in the plaintext.
+
+use std::ops::Range;
+
+use super::message::HttpMessageError;
+
+/// The logical body of a message, with the plaintext offset of each of its bytes.
+///
+/// Circuits digest the plaintext, so a witness computed on the logical body, e.g. the position of a
+/// JSON value, is mapped back to the plaintext bytes with `offsets`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedBody {
+  pub bytes:   Vec<u8>,
+  /// Offset in the plaintext of each byte of `bytes`
+  pub offsets: Vec<usize>,
+}
+
+impl DecodedBody {
+  /// A body sent as is, starting at `offset` in the plaintext.
+  pub fn identity(body: &[u8], offset: usize) -> Self {
+    Self { bytes: body.to_vec(), offsets: (offset..offset + body.len()).collect() }
+  }
+
+  /// Plaintext ranges holding the logical bytes in `range`, one per chunk they span.
+  pub fn plaintext_ranges(&self, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for offset in &self.offsets[range] {
+      match ranges.last_mut() {
+        Some(last) if last.end == *offset => last.end += 1,
+        _ => ranges.push(*offset..*offset + 1),
+      }
+    }
+    ranges
+  }
+}
+
+/// Decodes a chunked body starting at `offset` in the plaintext.
+///
+/// Chunk extensions and trailer fields are skipped, and bytes after the final CRLF are ignored.
+pub fn dechunk(body: &[u8], offset: usize) -> Result<DecodedBody, HttpMessageError> {
+  let mut decoded = DecodedBody { bytes: vec![], offsets: vec![] };
+  let mut idx = 0;
+  loop {
+    let line = crlf_line(body, idx, offset)?;
+    let size = &body[line.clone()];
+    let size = &size[..size.iter().position(|byte| *byte == b';').unwrap_or(size.len())];
+    let size = std::str::from_utf8(size)
+      .ok()
+      .map(|size| size.trim_matches([' ', '\t']))
+      // `from_str_radix` also takes a leading sign
+      .filter(|size| size.bytes().all(|byte| byte.is_ascii_hexdigit()))
+      .and_then(|size| usize::from_str_radix(size, 16).ok())
+      .ok_or(HttpMessageError::InvalidChunkSize { offset: offset + idx })?;
+    idx = line.end + 2;
+    if size == 0 {
+      break;
+    }
+
+    // The size is untrusted, a huge one must not overflow the end of the chunk
+    let truncated = || HttpMessageError::TruncatedChunk { offset: offset + idx };
+    let end = idx.checked_add(size).ok_or_else(truncated)?;
+    let data = idx..end;
+    if body.get(end..end.checked_add(2).ok_or_else(truncated)?) != Some(b"\r\n") {
+      return Err(truncated());
+    }
+    decoded.bytes.extend_from_slice(&body[data.clone()]);
+    decoded.offsets.extend(data.clone().map(|idx| offset + idx));
+    idx = data.end + 2;
+  }
+
+  // Trailer fields up to the empty line
+  loop {
+    let line = crlf_line(body, idx, offset)?;
+    idx = line.end + 2;
+    if line.is_empty() {
+      return Ok(decoded);
+    }
+  }
+}
+
+/// Range of the line starting at `start`, without its CRLF.
+fn crlf_line(body: &[u8], start: usize, offset: usize) -> Result<Range<usize>, HttpMessageError> {
+  body[start.min(body.len())..]
+    .windows(2)
+    .position(|window| window == b"\r\n")
+    .map(|len| start..start + len)
+    .ok_or(HttpMessageError::TruncatedChunk { offset: offset + start })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{http::message::HttpMessage, rstest};
+
+  const CHUNKED_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+    6\r\n{\"a\": \r\n6;ext=1\r\n\"bc\"}\n\r\n0\r\nx-trailer: 1\r\n\r\n";
+
+  #[test]
+  fn test_dechunk() {
+    let message = HttpMessage::parse(CHUNKED_RESPONSE).unwrap();
+    assert!(message.is_chunked());
+    let body = message.decoded_body().unwrap();
+    assert_eq!(body.bytes, b"{\"a\": \"bc\"}\n");
+
+    let start = message.body.range.start;
+    assert_eq!(body.offsets[0], start + 3);
+    assert_eq!(body.offsets[6], start + 20);
+    for (byte, offset) in body.bytes.iter().zip(&body.offsets) {
+      assert_eq!(CHUNKED_RESPONSE[*offset], *byte);
+    }
+    assert_eq!(body.plaintext_ranges(5..9), [start + 8..start + 9, start + 20..start + 23]);
+  }
+
+  #[test]
+  fn test_json_value_of_chunked_body() {
+    let message = HttpMessage::parse(CHUNKED_RESPONSE).unwrap();
+    let body = message.decoded_body().unwrap();
+    let value = crate::json::json_value_digest::<1>(&body.bytes, &[crate::json::JsonKey::String(
+      "a".to_string(),
+    )])
+    .unwrap();
+    assert_eq!(value, b"bc");
+  }
+
+  #[test]
+  fn test_decoded_body_not_chunked() {
+    let message = HttpMessage::parse(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi").unwrap();
+    assert!(!message.is_chunked());
+    assert_eq!(message.decoded_body().unwrap(), DecodedBody::identity(b"hi", 38));
+  }
+
+  #[rstest]
+  #[case::not_hex(b"zz\r\n", HttpMessageError::InvalidChunkSize { offset: 0 })]
+  #[case::empty_size(b"\r\n", HttpMessageError::InvalidChunkSize { offset: 0 })]
+  #[case::plus_sign(b"+5\r\nabcde\r\n0\r\n\r\n", HttpMessageError::InvalidChunkSize { offset: 0 })]
+  #[case::minus_zero(b"-0\r\n\r\n", HttpMessageError::InvalidChunkSize { offset: 0 })]
+  #[case::short_data(b"5\r\nabc", HttpMessageError::TruncatedChunk { offset: 3 })]
+  #[case::long_data(b"2\r\nabc\r\n0\r\n\r\n", HttpMessageError::TruncatedChunk { offset: 3 })]
+  #[case::oversized(b"FFFFFFFFFFFFFFFF\r\nab\r\n0\r\n\r\n", HttpMessageError::TruncatedChunk {
+    offset: 18,
+  })]
+  #[case::size_overflow(b"1FFFFFFFFFFFFFFFF\r\n", HttpMessageError::InvalidChunkSize { offset: 0 })]
+  #[case::no_last_chunk(b"1\r\na\r\n", HttpMessageError::TruncatedChunk { offset: 6 })]
+  #[case::no_final_crlf(b"0\r\n", HttpMessageError::TruncatedChunk { offset: 3 })]
+  fn test_dechunk_errors(#[case] body: &[u8], #[case] error: HttpMessageError) {
+    assert_eq!(dechunk(body, 0), Err(error));
+  }
+}
@@ -0,0 +1,474 @@
+//! Structure of an HTTP message, parsed in a single pass with the byte range of every part.
+
+use std::ops::Range;
+
+use thiserror::Error;
+
+use super::{
+  chunked::{dechunk, DecodedBody},
+  *,
+};
+
+/// A malformed HTTP message, with the offset of the offending byte where there is one.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum HttpMessageError {
+  #[error("http message has no start line")]
+  EmptyStartLine,
+  #[error("http message headers are not terminated by an empty line")]
+  UnterminatedHeaders,
+  #[error("bare LF at byte {offset}, lines must end with CRLF")]
+  BareLf { offset: usize },
+  #[error("bare CR at byte {offset}, lines must end with CRLF")]
+  BareCr { offset: usize },
+  #[error("start line is not three parts separated by spaces")]
+  MalformedStartLine,
+  #[error("status code at byte {offset} is not three digits")]
+  InvalidStatusCode { offset: usize },
+  #[error("invalid chunk size at byte {offset}")]
+  InvalidChunkSize { offset: usize },
+  #[error("chunk at byte {offset} is cut off or not terminated by CRLF")]
+  TruncatedChunk { offset: usize },
+  #[error("chunked body at byte {offset} cannot be proven, the circuits digest the chunk framing")]
+  ChunkedBody { offset: usize },
+  #[error("body is cut off at byte {offset}")]
+  TruncatedBody { offset: usize },
+  #[error("invalid content-length at byte {offset}")]
+  InvalidContentLength { offset: usize },
+  #[error("content-length is {content_length} but the body has {body_length} bytes")]
+  ContentLengthMismatch { content_length: usize, body_length: usize },
+  #[error("header line at byte {offset} has no colon")]
+  MissingColon { offset: usize },
+  #[error("header line at byte {offset} has an empty name")]
+  EmptyHeaderName { offset: usize },
+}
+
+/// Bytes of a part of the message, with their range in the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span<'a> {
+  pub range: Range<usize>,
+  pub bytes: &'a [u8],
+}
+
+impl<'a> Span<'a> {
+  fn new(message: &'a [u8], range: Range<usize>) -> Self {
+    Self { bytes: &message[range.clone()], range }
+  }
+}
+
+/// Request line `method target version`, e.g. `GET / HTTP/1.1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestLine<'a> {
+  pub method:  Span<'a>,
+  pub target:  Span<'a>,
+  pub version: Span<'a>,
+}
+
+/// Status line `version status reason`, e.g. `HTTP/1.1 200 OK`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine<'a> {
+  pub version: Span<'a>,
+  /// Three digit status code
+  pub status:  Span<'a>,
+  /// Reason phrase, may be empty or contain spaces
+  pub reason:  Span<'a>,
+}
+
+impl StatusLine<'_> {
+  /// The status code as a number, e.g. `200`.
+  pub fn code(&self) -> u16 {
+    self.status.bytes.iter().fold(0, |code, digit| code * 10 + u16::from(digit - b'0'))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpHeader<'a> {
+  /// The whole line, without the CRLF, as digested by `HTTPVerification`
+  pub line:  Span<'a>,
+  pub name:  Span<'a>,
+  /// The value, without the whitespace around it
+  pub value: Span<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpMessage<'a> {
+  /// Request or status line, without the CRLF
+  pub start_line: Span<'a>,
+  pub headers:    Vec<HttpHeader<'a>>,
+  /// Everything after the empty line ending the headers
+  pub body:       Span<'a>,
+}
+
+impl<'a> HttpMessage<'a> {
+  /// Parses the start line, headers and body of an HTTP request or response with CRLF line endings.
+  pub fn parse(bytes: &'a [u8]) -> Result<Self, HttpMessageError> {
+    Self::parse_with(bytes, LineEndings::Strict)
+  }
+
+  /// Parses the start line, headers and body of an HTTP request or response.
+  pub fn parse_with(bytes: &'a [u8], line_endings: LineEndings) -> Result<Self, HttpMessageError> {
+    match Self::parse_lines(bytes, line_endings) {
+      (message, None) => Ok(message),
+      (_, Some(err)) => Err(err),
+    }
+  }
+
+  /// Parses the complete lines at the start of `bytes`, which may end before the empty line, e.g.
+  /// the first plaintext chunk of a response.
+  ///
+  /// Lines are read up to the first one that is cut off or would fail [`HttpMessage::parse_with`].
+  /// The body is empty unless the empty line ending the headers was reached, and so is the start
+  /// line if there is no complete line.
+  pub fn parse_head(bytes: &'a [u8], line_endings: LineEndings) -> Self {
+    Self::parse_lines(bytes, line_endings).0
+  }
+
+  /// The message up to the first error, and that error.
+  fn parse_lines(bytes: &'a [u8], line_endings: LineEndings) -> (Self, Option<HttpMessageError>) {
+    let mut lines = vec![];
+    let mut line_start = 0;
+    let mut idx = 0;
+    let body_start = loop {
+      // End of the line at `idx` and start of the next one
+      let (line_end, next) = match bytes.get(idx) {
+        None => break Err(HttpMessageError::UnterminatedHeaders),
+        Some(b'\r') => match bytes.get(idx + 1) {
+          None => break Err(HttpMessageError::UnterminatedHeaders),
+          Some(b'\n') => (idx, idx + 2),
+          Some(_) => break Err(HttpMessageError::BareCr { offset: idx }),
+        },
+        Some(b'\n') if line_endings == LineEndings::Lenient => (idx, idx + 1),
+        Some(b'\n') => break Err(HttpMessageError::BareLf { offset: idx }),
+        Some(_) => {
+          idx += 1;
+          continue;
+        },
+      };
+      if line_end == line_start {
+        if lines.is_empty() {
+          break Err(HttpMessageError::EmptyStartLine);
+        }
+        break Ok(next);
+      }
+      lines.push(line_start..line_end);
+      idx = next;
+      line_start = next;
+    };
+
+    let mut lines = lines.into_iter();
+    let start_line = Span::new(bytes, lines.next().unwrap_or(0..0));
+    let mut error = body_start.as_ref().err().cloned();
+    let mut headers = vec![];
+    for line in lines {
+      let offset = line.start;
+      let header = match header_ranges(&bytes[line.clone()]) {
+        None => Err(HttpMessageError::MissingColon { offset }),
+        Some((name, _)) if name.is_empty() => Err(HttpMessageError::EmptyHeaderName { offset }),
+        Some((name, value)) => Ok(HttpHeader {
+          name:  Span::new(bytes, offset + name.start..offset + name.end),
+          value: Span::new(bytes, offset + value.start..offset + value.end),
+          line:  Span::new(bytes, line),
+        }),
+      };
+      match header {
+        Ok(header) => headers.push(header),
+        Err(err) => {
+          error.get_or_insert(err);
+          break;
+        },
+      }
+    }
+
+    let body = match (&error, body_start) {
+      (None, Ok(body_start)) => body_start..bytes.len(),
+      _ => bytes.len()..bytes.len(),
+    };
+    (Self { start_line, headers, body: Span::new(bytes, body) }, error)
+  }
+
+  /// The start line of a request, split into its parts.
+  pub fn request_line(&self) -> Result<RequestLine<'a>, HttpMessageError> {
+    let [method, target, version] = self.start_line_parts()?;
+    if version.bytes.contains(&b' ') {
+      return Err(HttpMessageError::MalformedStartLine);
+    }
+    Ok(RequestLine { method, target, version })
+  }
+
+  /// The start line of a response, split into its parts.
+  pub fn status_line(&self) -> Result<StatusLine<'a>, HttpMessageError> {
+    let [version, status, reason] = self.start_line_parts()?;
+    if status.bytes.len() != 3 || !status.bytes.iter().all(u8::is_ascii_digit) {
+      return Err(HttpMessageError::InvalidStatusCode { offset: status.range.start });
+    }
+    Ok(StatusLine { version, status, reason })
+  }
+
+  /// Splits the start line at its first two spaces, where the parser moves from
+  /// [`StartLineLocation::Beginning`] to [`StartLineLocation::Middle`] and then to
+  /// [`StartLineLocation::End`]. The last part keeps any further spaces.
+  fn start_line_parts(&self) -> Result<[Span<'a>; 3], HttpMessageError> {
+    let (range, bytes) = (&self.start_line.range, self.start_line.bytes);
+    let mut spaces =
+      bytes.iter().enumerate().filter(|(_, byte)| **byte == b' ').map(|(idx, _)| idx);
+    let (Some(first), Some(second)) = (spaces.next(), spaces.next()) else {
+      return Err(HttpMessageError::MalformedStartLine);
+    };
+    if first == 0 || second == first + 1 {
+      return Err(HttpMessageError::MalformedStartLine);
+    }
+    let span = |part: Range<usize>| Span {
+      bytes: &bytes[part.clone()],
+      range: range.start + part.start..range.start + part.end,
+    };
+    Ok([span(0..first), span(first + 1..second), span(second + 1..bytes.len())])
+  }
+
+  /// Whether the body is sent with `Transfer-Encoding: chunked`, i.e. chunked is the last coding.
+  pub fn is_chunked(&self) -> bool {
+    self.headers_named(b"transfer-encoding", HeaderNameMatch::CaseInsensitive).any(|(_, header)| {
+      header
+        .value
+        .bytes
+        .rsplit(|byte| *byte == b',')
+        .next()
+        .is_some_and(|coding| coding.trim_ascii().eq_ignore_ascii_case(b"chunked"))
+    })
+  }
+
+  /// The logical body, dechunked if the message [`is_chunked`](Self::is_chunked).
+  pub fn decoded_body(&self) -> Result<DecodedBody, HttpMessageError> {
+    if self.is_chunked() {
+      dechunk(self.body.bytes, self.body.range.start)
+    } else {
+      Ok(DecodedBody::identity(self.body.bytes, self.body.range.start))
+    }
+  }
+
+  /// The `Content-Length` of the message, `None` if it has none. Repeated headers must agree.
+  pub fn content_length(&self) -> Result<Option<usize>, HttpMessageError> {
+    let mut content_length = None;
+    for (_, header) in self.headers_named(b"content-length", HeaderNameMatch::CaseInsensitive) {
+      let invalid = HttpMessageError::InvalidContentLength { offset: header.value.range.start };
+      let value = header.value.bytes;
+      if value.is_empty() || !value.iter().all(u8::is_ascii_digit) {
+        return Err(invalid);
+      }
+      let value = std::str::from_utf8(value).ok().and_then(|value| value.parse().ok());
+      match (content_length, value) {
+        (_, None) => return Err(invalid),
+        (Some(existing), Some(value)) if existing != value => return Err(invalid),
+        (_, value) => content_length = value,
+      }
+    }
+    Ok(content_length)
+  }
+
+  /// Checks the body has exactly `Content-Length` bytes, which catches a transcript cut off in the
+  /// body or with extra bytes after it. A chunked body, whose `Content-Length` is ignored, is
+  /// decoded instead and fails with [`HttpMessageError::TruncatedBody`] when a chunk or the last
+  /// chunk is missing. Messages with neither are not checked.
+  pub fn validate_content_length(&self) -> Result<(), HttpMessageError> {
+    if self.is_chunked() {
+      return self.decoded_body().map(|_| ()).map_err(|err| match err {
+        HttpMessageError::TruncatedChunk { offset } => HttpMessageError::TruncatedBody { offset },
+        err => err,
+      });
+    }
+    match self.content_length()? {
+      Some(content_length) if content_length != self.body.bytes.len() =>
+        Err(HttpMessageError::ContentLengthMismatch {
+          content_length,
+          body_length: self.body.bytes.len(),
+        }),
+      _ => Ok(()),
+    }
+  }
+
+  /// Index and header of every header named `name`, comparing names as `name_match` says.
+  pub fn headers_named<'b>(
+    &'b self,
+    name: &'b [u8],
+    name_match: HeaderNameMatch,
+  ) -> impl Iterator<Item = (usize, &'b HttpHeader<'a>)> + 'b {
+    self
+      .headers
+      .iter()
+      .enumerate()
+      .filter(move |(_, header)| name_match.matches(header.name.bytes, name))
+  }
+}
+
+/// Ranges of the name and of the value of a header line, the value without the optional
+/// whitespace (spaces and tabs) around it. `None` if the line has no colon.
+pub(crate) fn header_ranges(line: &[u8]) -> Option<(Range<usize>, Range<usize>)> {
+  let colon = line.iter().position(|byte| *byte == b':')?;
+  let is_whitespace = |byte: &u8| *byte == b' ' || *byte == b'\t';
+  let value = &line[colon + 1..];
+  let start = value.iter().position(|byte| !is_whitespace(byte)).unwrap_or(value.len());
+  let end = value.iter().rposition(|byte| !is_whitespace(byte)).map_or(start, |idx| idx + 1);
+  Some((0..colon, colon + 1 + start..colon + 1 + end))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_http_message() {
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let message = HttpMessage::parse(plaintext).unwrap();
+
+    assert_eq!(message.start_line.bytes, RESPONSE_START_LINE.as_bytes());
+    assert_eq!(message.start_line.range, 0..RESPONSE_START_LINE.len());
+    assert_eq!(message.headers[0].line.bytes, RESPONSE_HEADER_0.as_bytes());
+    assert_eq!(message.headers[0].line.range.start, RESPONSE_START_LINE.len() + 2);
+    assert_eq!(message.headers[0].name.bytes, b"content-type");
+    assert_eq!(message.headers[0].value.bytes, b"application/json; charset=utf-8");
+    assert_eq!(message.headers[1].line.bytes, RESPONSE_HEADER_1.as_bytes());
+    assert_eq!(message.headers[2].name.bytes, b"Transfer-Encoding");
+    assert_eq!(message.body.bytes, RESPONSE_BODY.as_bytes());
+    assert_eq!(message.body.range, plaintext.len() - RESPONSE_BODY.len()..plaintext.len());
+    for header in &message.headers {
+      assert_eq!(&plaintext[header.name.range.clone()], header.name.bytes);
+      assert_eq!(&plaintext[header.value.range.clone()], header.value.bytes);
+    }
+  }
+
+  #[test]
+  fn test_parse_http_message_header_whitespace() {
+    let message = HttpMessage::parse(b"GET / HTTP/1.1\r\na:b\r\nc: \t d \r\ne:\r\n\r\n").unwrap();
+    let headers = message
+      .headers
+      .iter()
+      .map(|header| (header.name.bytes, header.value.bytes))
+      .collect::<Vec<_>>();
+    assert_eq!(headers, [
+      (b"a".as_slice(), b"b".as_slice()),
+      (b"c".as_slice(), b"d".as_slice()),
+      (b"e".as_slice(), b"".as_slice())
+    ]);
+    assert_eq!(message.headers[1].value.range, 26..27);
+    assert!(message.body.bytes.is_empty());
+  }
+
+  #[test]
+  fn test_status_line() {
+    let message = HttpMessage::parse(RESPONSE_PLAINTEXT.as_bytes()).unwrap();
+    let status_line = message.status_line().unwrap();
+    assert_eq!(status_line.version.bytes, b"HTTP/1.1");
+    assert_eq!(status_line.status.bytes, b"200");
+    assert_eq!(status_line.status.range, 9..12);
+    assert_eq!(status_line.reason.bytes, b"OK");
+    assert_eq!(status_line.code(), 200);
+
+    let message = HttpMessage::parse(b"HTTP/1.1 404 Not Found\r\n\r\n").unwrap();
+    assert_eq!(message.status_line().unwrap().reason.bytes, b"Not Found");
+    let message = HttpMessage::parse(b"HTTP/1.1 204 \r\n\r\n").unwrap();
+    assert!(message.status_line().unwrap().reason.bytes.is_empty());
+  }
+
+  #[test]
+  fn test_request_line() {
+    let message = HttpMessage::parse(b"GET /api/v1?q=1 HTTP/1.1\r\nhost: a\r\n\r\n").unwrap();
+    let request_line = message.request_line().unwrap();
+    assert_eq!(request_line.method.bytes, b"GET");
+    assert_eq!(request_line.target.bytes, b"/api/v1?q=1");
+    assert_eq!(request_line.target.range, 4..15);
+    assert_eq!(request_line.version.bytes, b"HTTP/1.1");
+  }
+
+  #[rstest]
+  #[case::one_part(b"HTTP/1.1\r\n\r\n", HttpMessageError::MalformedStartLine)]
+  #[case::two_parts(b"HTTP/1.1 200\r\n\r\n", HttpMessageError::MalformedStartLine)]
+  #[case::leading_space(b" HTTP/1.1 200 OK\r\n\r\n", HttpMessageError::MalformedStartLine)]
+  #[case::double_space(b"HTTP/1.1  200 OK\r\n\r\n", HttpMessageError::MalformedStartLine)]
+  #[case::short_code(b"HTTP/1.1 20 OK\r\n\r\n", HttpMessageError::InvalidStatusCode { offset: 9 })]
+  #[case::letters(b"HTTP/1.1 2OO OK\r\n\r\n", HttpMessageError::InvalidStatusCode { offset: 9 })]
+  fn test_status_line_errors(#[case] bytes: &[u8], #[case] error: HttpMessageError) {
+    assert_eq!(HttpMessage::parse(bytes).unwrap().status_line(), Err(error));
+  }
+
+  #[test]
+  fn test_request_line_errors() {
+    let message = HttpMessage::parse(b"GET / HTTP/1.1 extra\r\n\r\n").unwrap();
+    assert_eq!(message.request_line(), Err(HttpMessageError::MalformedStartLine));
+  }
+
+  #[test]
+  fn test_parse_http_message_lenient() {
+    let bytes = b"HTTP/1.1 200 OK\na: b\r\nc: d\n\nbody";
+    assert_eq!(HttpMessage::parse(bytes), Err(HttpMessageError::BareLf { offset: 15 }));
+
+    let message = HttpMessage::parse_with(bytes, LineEndings::Lenient).unwrap();
+    assert_eq!(message.start_line.bytes, b"HTTP/1.1 200 OK");
+    assert_eq!(message.headers[0].line.range, 16..20);
+    assert_eq!(message.headers[1].line.bytes, b"c: d");
+    assert_eq!(message.body.range, 28..32);
+
+    let bytes = b"HTTP/1.1 200 OK\ra: b\n\n";
+    assert_eq!(
+      HttpMessage::parse_with(bytes, LineEndings::Lenient),
+      Err(HttpMessageError::BareCr { offset: 15 })
+    );
+  }
+
+  #[rstest]
+  #[case::matches(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi", Ok(()))]
+  #[case::no_content_length(b"HTTP/1.1 200 OK\r\n\r\nhi", Ok(()))]
+  #[case::chunked(
+    b"HTTP/1.1 200 OK\r\ncontent-length: 9\r\ntransfer-encoding: chunked\r\n\r\n0\r\n\r\n",
+    Ok(())
+  )]
+  #[case::chunked_truncated(
+    b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n5\r\nab",
+    Err(HttpMessageError::TruncatedBody { offset: 50 })
+  )]
+  #[case::chunked_no_last_chunk(
+    b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n2\r\nab\r\n",
+    Err(HttpMessageError::TruncatedBody { offset: 54 })
+  )]
+  #[case::chunked_invalid_size(
+    b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\nzz\r\n",
+    Err(HttpMessageError::InvalidChunkSize { offset: 47 })
+  )]
+  #[case::repeated(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\ncontent-length: 2\r\n\r\nhi", Ok(()))]
+  #[case::truncated(
+    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhi",
+    Err(HttpMessageError::ContentLengthMismatch { content_length: 5, body_length: 2 })
+  )]
+  #[case::too_long(
+    b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nhi",
+    Err(HttpMessageError::ContentLengthMismatch { content_length: 1, body_length: 2 })
+  )]
+  #[case::not_a_number(
+    b"HTTP/1.1 200 OK\r\nContent-Length: -2\r\n\r\nhi",
+    Err(HttpMessageError::InvalidContentLength { offset: 33 })
+  )]
+  #[case::conflicting(
+    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nContent-Length: 3\r\n\r\nhi",
+    Err(HttpMessageError::InvalidContentLength { offset: 52 })
+  )]
+  fn test_validate_content_length(
+    #[case] bytes: &[u8],
+    #[case] expected: Result<(), HttpMessageError>,
+  ) {
+    assert_eq!(HttpMessage::parse(bytes).unwrap().validate_content_length(), expected);
+  }
+
+  #[rstest]
+  #[case::empty(b"", HttpMessageError::UnterminatedHeaders)]
+  #[case::no_start_line(b"\r\n\r\n", HttpMessageError::EmptyStartLine)]
+  #[case::unterminated(b"HTTP/1.1 200 OK\r\na: b\r\n", HttpMessageError::UnterminatedHeaders)]
+  #[case::trailing_cr(b"HTTP/1.1 200 OK\r", HttpMessageError::UnterminatedHeaders)]
+  #[case::bare_lf(b"HTTP/1.1 200 OK\na: b\r\n\r\n", HttpMessageError::BareLf { offset: 15 })]
+  #[case::bare_cr(b"HTTP/1.1 200 OK\ra: b\r\n\r\n", HttpMessageError::BareCr { offset: 15 })]
+  #[case::missing_colon(b"HTTP/1.1 200 OK\r\nab\r\n\r\n", HttpMessageError::MissingColon {
+    offset: 17,
+  })]
+  #[case::empty_name(b"HTTP/1.1 200 OK\r\n: b\r\n\r\n", HttpMessageError::EmptyHeaderName {
+    offset: 17,
+  })]
+  fn test_parse_http_message_errors(#[case] bytes: &[u8], #[case] error: HttpMessageError) {
+    assert_eq!(HttpMessage::parse(bytes), Err(error));
+  }
+}
@@ -1,6 +1,9 @@
 use serde::Serialize;
 
+use self::message::HttpMessage;
 use super::*;
+pub mod chunked;
+pub mod message;
 pub mod parser;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,6 +78,47 @@ impl From<HttpMachine> for RawHttpMachine {
   }
 }
 
+/// Recovers the parser state from a circuit `machine_state`, e.g. to resume parsing from a fold.
+///
+/// Fails with [`WitnessGeneratorError::InvalidHttpMachine`] on a flag combination the parser never
+/// reaches. The position in the line is not recovered here, as it depends on the polynomial input,
+/// see [`parser::StreamingParser::resume`].
+impl TryFrom<RawHttpMachine> for HttpMachine {
+  type Error = WitnessGeneratorError;
+
+  fn try_from(raw: RawHttpMachine) -> Result<Self, Self::Error> {
+    let invalid = |reason: &str| WitnessGeneratorError::InvalidHttpMachine(reason.to_string());
+    let machine = HttpMachine {
+      header_num:    raw.header_num().ok_or_else(|| invalid("invalid header counter"))?,
+      status:        raw.status().ok_or_else(|| invalid("no parser status"))?,
+      line_digest:   raw.line_digest,
+      line_monomial: raw.line_monomial,
+    };
+    // More than one status flag set. Any space count past the end of the start line is the end.
+    let mut expected = RawHttpMachine::from(machine);
+    if machine.status == HttpStatus::ParsingStart(StartLineLocation::End) {
+      expected.parsing_start = raw.parsing_start;
+    }
+    if expected != raw {
+      return Err(invalid("conflicting status flags"));
+    }
+    match machine.status {
+      HttpStatus::ParsingStart(_) | HttpStatus::ParsingBody if machine.header_num != 0 =>
+        Err(invalid("header counter outside of the headers")),
+      HttpStatus::ParsingHeader(_) if machine.header_num == 0 =>
+        Err(invalid("header counter is zero in a header")),
+      HttpStatus::ParsingBody | HttpStatus::LineStatus(_)
+        if machine.line_digest != F::ZERO || machine.line_monomial != F::ZERO =>
+        Err(invalid("line digest outside of a line")),
+      HttpStatus::ParsingStart(StartLineLocation::Middle | StartLineLocation::End)
+      | HttpStatus::ParsingHeader(_)
+        if machine.line_monomial == F::ZERO =>
+        Err(invalid("line monomial is zero in a line")),
+      _ => Ok(machine),
+    }
+  }
+}
+
 impl RawHttpMachine {
   pub fn initial_state() -> Self {
     Self { parsing_start: F::ONE, line_monomial: F::ONE, ..Default::default() }
@@ -92,6 +136,39 @@ impl RawHttpMachine {
       self.line_monomial,
     ]
   }
+
+  /// Recovers the parser status from the flags, `None` if they do not encode a parser status.
+  pub(crate) fn status(&self) -> Option<HttpStatus> {
+    let status = if self.parsing_body == F::ONE {
+      HttpStatus::ParsingBody
+    } else if self.line_status != F::ZERO {
+      HttpStatus::LineStatus(match field_element_to_u64(self.line_status)? {
+        1 => LineStatus::CR,
+        2 => LineStatus::CRLF,
+        3 => LineStatus::CRLFCR,
+        _ => return None,
+      })
+    } else if self.parsing_start != F::ZERO {
+      // The circuit keeps counting spaces after the third part, e.g. in `404 Not Found`
+      HttpStatus::ParsingStart(match field_element_to_u64(self.parsing_start)? {
+        1 => StartLineLocation::Beginning,
+        2 => StartLineLocation::Middle,
+        _ => StartLineLocation::End,
+      })
+    } else if self.parsing_field_name == F::ONE {
+      HttpStatus::ParsingHeader(NameOrValue::Name)
+    } else if self.parsing_field_value == F::ONE {
+      HttpStatus::ParsingHeader(NameOrValue::Value)
+    } else {
+      return None;
+    };
+    Some(status)
+  }
+
+  /// Index of the header being parsed, `None` if the counter is not a small integer.
+  pub(crate) fn header_num(&self) -> Option<usize> {
+    field_element_to_u64(self.parsing_header).and_then(|header_num| header_num.try_into().ok())
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -122,16 +199,164 @@ pub enum LineStatus {
   CRLFCR,
 }
 
+/// Line endings accepted when parsing HTTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEndings {
+  /// Only CRLF, as `HTTPVerification` expects
+  #[default]
+  Strict,
+  /// CRLF or a bare LF, as some servers and edited fixtures send.
+  ///
+  /// The `HTTPVerification` circuit only moves through `CR` → `CRLF` → `CRLFCR` and never reaches
+  /// the body of such a message, so witnesses parsed in this mode need a circuit variant that also
+  /// accepts bare LF line endings.
+  Lenient,
+}
+
 pub enum HttpMaskType {
   StartLine,
   Header(usize),
   Body,
 }
 
-// TODO: Note, HTTP does not require a `:` and space between the name and value of a header, so we
-// will have to deal with this somehow, but for now I'm assuming there's a space
-pub fn headers_to_bytes(headers: &HashMap<String, String>) -> impl Iterator<Item = Vec<u8>> + '_ {
-  headers.iter().map(|(k, v)| format!("{}: {}", k.clone(), v.clone()).as_bytes().to_vec())
+/// HTTP headers in the order they were given, e.g. the order they appear in the response.
+///
+/// Unlike a [`HashMap`], iterating yields the same order on every run, so `main_digests` built
+/// from the headers are stable. The header verification lock is a sum and does not depend on it.
+///
+/// A name may be repeated, e.g. for several `set-cookie` headers, each value is then its own
+/// header line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+  pub fn new() -> Self { Self::default() }
+
+  /// Sets `name` to `value`, keeping the position of the first value of `name` if it is already
+  /// present and dropping its other values. Returns the first value replaced.
+  pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> Option<String> {
+    let (name, value) = (name.into(), value.into());
+    let Some(position) = self.0.iter().position(|(existing, _)| *existing == name) else {
+      self.0.push((name, value));
+      return None;
+    };
+    let replaced = std::mem::replace(&mut self.0[position].1, value);
+    let later = self.0.split_off(position + 1);
+    self.0.extend(later.into_iter().filter(|(existing, _)| *existing != name));
+    Some(replaced)
+  }
+
+  /// Adds a header line `name: value`, after any existing value of `name`.
+  pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    self.0.push((name.into(), value.into()));
+  }
+
+  /// First value of `name`.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.0.iter().find(|(existing, _)| existing == name).map(|(_, value)| value.as_str())
+  }
+
+  /// Every value of `name`, in order.
+  pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    self.0.iter().filter(move |(existing, _)| existing == name).map(|(_, value)| value.as_str())
+  }
+
+  /// Number of header lines, counting every value of a repeated name.
+  pub fn len(&self) -> usize { self.0.len() }
+
+  pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+    self.0.iter().map(|(name, value)| (name, value))
+  }
+
+  /// Renames every header to the casing its name has in `plaintext`, so the header lines match the
+  /// bytes the circuit digests. The `n`-th value of a name takes the casing of the `n`-th matching
+  /// line, headers not found in `plaintext` are kept as they are.
+  pub fn with_casing_of(&self, plaintext: &[u8]) -> Self {
+    let mut seen: Vec<&str> = vec![];
+    self
+      .iter()
+      .map(|(name, value)| {
+        let occurrence = seen.iter().filter(|seen| seen.eq_ignore_ascii_case(name)).count();
+        seen.push(name);
+        let found = compute_http_header_witnesses(
+          plaintext,
+          name.as_bytes(),
+          HeaderNameMatch::CaseInsensitive,
+        )
+        .into_iter()
+        .nth(occurrence)
+        .and_then(|(_, line)| {
+          let found = line.split(|byte| *byte == b':').next()?;
+          String::from_utf8(found.to_vec()).ok()
+        });
+        (found.unwrap_or_else(|| name.clone()), value.clone())
+      })
+      .collect()
+  }
+}
+
+/// Appends every header, so repeated names keep all their values.
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for Headers {
+  fn from_iter<T: IntoIterator<Item = (N, V)>>(iter: T) -> Self {
+    let mut headers = Self::new();
+    for (name, value) in iter {
+      headers.append(name, value);
+    }
+    headers
+  }
+}
+
+impl<N: Into<String>, V: Into<String>, const L: usize> From<[(N, V); L]> for Headers {
+  fn from(headers: [(N, V); L]) -> Self { headers.into_iter().collect() }
+}
+
+/// Sorts the headers by name, as a [`HashMap`] has no order to preserve.
+impl From<HashMap<String, String>> for Headers {
+  fn from(headers: HashMap<String, String>) -> Self {
+    let mut headers = headers.into_iter().collect::<Vec<_>>();
+    headers.sort();
+    Self(headers)
+  }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+  type IntoIter = std::iter::Map<
+    std::slice::Iter<'a, (String, String)>,
+    fn(&'a (String, String)) -> (&'a String, &'a String),
+  >;
+  type Item = (&'a String, &'a String);
+
+  fn into_iter(self) -> Self::IntoIter { self.0.iter().map(|(name, value)| (name, value)) }
+}
+
+/// Serializes as the `Record<string, string[]>` of the TS `Manifest`, names in order of first
+/// appearance.
+impl Serialize for Headers {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where S: serde::Serializer {
+    let mut names: Vec<&String> = vec![];
+    for (name, _) in self {
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+    serializer
+      .collect_map(names.into_iter().map(|name| (name, self.get_all(name).collect::<Vec<_>>())))
+  }
+}
+
+/// Header lines `name: value`, in the iteration order of `headers`.
+///
+/// Accepts [`Headers`] as well as a [`HashMap`], whose order changes between runs. HTTP does not
+/// require a single space after the colon, use [`plaintext_header_lines`] to digest the separator
+/// the plaintext actually has.
+pub fn headers_to_bytes<'a, I>(headers: I) -> impl Iterator<Item = Vec<u8>> + 'a
+where
+  I: IntoIterator<Item = (&'a String, &'a String)>,
+  I::IntoIter: 'a, {
+  headers.into_iter().map(|(k, v)| format!("{}: {}", k.clone(), v.clone()).as_bytes().to_vec())
 }
 
 /// compute private inputs for the HTTP circuit.
@@ -139,119 +364,263 @@ pub fn headers_to_bytes(headers: &HashMap<String, String>) -> impl Iterator<Item
 /// - `plaintext`: the plaintext HTTP request/response padded with `-1` to nearest power of 2
 /// - `mask_at`: the [`HttpMaskType`] of the HTTP request/response to mask
 /// # Returns
-/// - the masked HTTP request/response
+/// - the masked HTTP request/response, empty if it is not there. `plaintext` may end before the
+///   body, e.g. a first ciphertext chunk, its lines are read as [`HttpMessage::parse_head`] does
 pub fn compute_http_witness(plaintext: &[u8], mask_at: HttpMaskType) -> Vec<u8> {
-  let mut result = Vec::new();
-  match mask_at {
-    HttpMaskType::StartLine => {
-      // Find the first CRLF sequence
-      for i in 0..plaintext.len().saturating_sub(1) {
-        if plaintext[i] == b'\r' && plaintext[i + 1] == b'\n' {
-          result = plaintext[..i].to_vec();
-          break;
-        }
-      }
-    },
-    HttpMaskType::Header(idx) => {
-      let mut current_header = 0;
-      let mut start_pos = 0;
-
-      // Skip the start line
-      for i in 0..plaintext.len().saturating_sub(1) {
-        if plaintext[i] == b'\r' && plaintext[i + 1] == b'\n' {
-          start_pos = i + 2;
-          break;
-        }
-      }
+  compute_http_witness_with(plaintext, mask_at, LineEndings::Strict)
+}
 
-      // Find the specified header
-      let mut header_start_pos = start_pos;
-      for i in start_pos..plaintext.len().saturating_sub(1) {
-        if plaintext[i] == b'\r' && plaintext[i + 1] == b'\n' {
-          if current_header == idx {
-            // Copy the header line (including CRLF)
-            result = plaintext[header_start_pos..i].to_vec();
-            break;
-          }
-
-          // Check for end of headers (double CRLF)
-          if i + 3 < plaintext.len() && plaintext[i + 2] == b'\r' && plaintext[i + 3] == b'\n' {
-            break;
-          }
-
-          current_header += 1;
-          header_start_pos = i + 2;
-        }
-      }
-    },
-    HttpMaskType::Body => {
-      // Find double CRLF that marks start of body
-      for i in 0..plaintext.len().saturating_sub(3) {
-        if plaintext[i] == b'\r'
-          && plaintext[i + 1] == b'\n'
-          && plaintext[i + 2] == b'\r'
-          && plaintext[i + 3] == b'\n'
-        {
-          // Copy everything after the double CRLF
-          let body_start = i + 4;
-          if body_start < plaintext.len() {
-            result = plaintext[body_start..].to_vec();
-          }
-          break;
-        }
-      }
-    },
+/// [`compute_http_witness`] accepting the given [`LineEndings`].
+pub fn compute_http_witness_with(
+  plaintext: &[u8],
+  mask_at: HttpMaskType,
+  line_endings: LineEndings,
+) -> Vec<u8> {
+  let message = HttpMessage::parse_head(plaintext, line_endings);
+  match mask_at {
+    HttpMaskType::StartLine => message.start_line.bytes.to_vec(),
+    HttpMaskType::Header(idx) =>
+      message.headers.get(idx).map(|header| header.line.bytes.to_vec()).unwrap_or_default(),
+    HttpMaskType::Body => message.body.bytes.to_vec(),
   }
-  result
 }
 
-pub fn compute_http_header_witness(plaintext: &[u8], name: &[u8]) -> (usize, Vec<u8>) {
-  let mut result = Vec::new();
+/// Splits a header line into its name and value, dropping the optional whitespace (spaces and tabs)
+/// around the value.
+pub fn split_header_line(line: &[u8]) -> Option<(&[u8], &[u8])> {
+  let (name, value) = message::header_ranges(line)?;
+  Some((&line[name], &line[value]))
+}
 
-  let mut current_header = 0;
-  let mut current_header_name = vec![];
-  let mut start_pos = 0;
+/// Header lines of `headers` exactly as they appear in `plaintext`, in the iteration order of
+/// `headers`.
+///
+/// A line matches a header when its name is the same up to ASCII case, as header names are in
+/// HTTP, and its value is the same once the whitespace around it is dropped, so `Foo:bar` or
+/// `foo:\t bar` both match the header `foo` with value `bar`.
+///
+/// Fails if `plaintext` is not an HTTP message, or with [`WitnessGeneratorError::InvalidManifest`]
+/// for a header it does not have.
+pub fn plaintext_header_lines<'a, I>(
+  plaintext: &[u8],
+  headers: I,
+) -> Result<Vec<Vec<u8>>, WitnessGeneratorError>
+where
+  I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+  let message = HttpMessage::parse(plaintext)?;
+  headers
+    .into_iter()
+    .map(|(name, value)| {
+      message
+        .headers_named(name.as_bytes(), HeaderNameMatch::CaseInsensitive)
+        .find(|(_, header)| header.value.bytes == value.as_bytes())
+        .map(|(_, header)| header.line.bytes.to_vec())
+        .ok_or_else(|| {
+          WitnessGeneratorError::InvalidManifest(format!(
+            "header `{name}: {value}` is not in the plaintext"
+          ))
+        })
+    })
+    .collect()
+}
 
-  // Skip the start line
-  for i in 1..plaintext.len().saturating_sub(1) {
-    if plaintext[i] == b'\r' && plaintext[i + 1] == b'\n' {
-      start_pos = i + 2;
-      break;
-    }
-  }
+/// How a header name given by the caller is compared with the names in the plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderNameMatch {
+  /// Byte for byte
+  #[default]
+  Exact,
+  /// ASCII case-insensitively, as header names are in HTTP
+  CaseInsensitive,
+}
 
-  // Find the specified header
-  let mut header_start_pos = start_pos;
-  for i in start_pos..plaintext.len().saturating_sub(1) {
-    // find header name
-    if plaintext[i] == b':' {
-      current_header_name = plaintext[header_start_pos..i].to_vec();
+impl HeaderNameMatch {
+  pub(crate) fn matches(self, found: &[u8], name: &[u8]) -> bool {
+    match self {
+      Self::Exact => found == name,
+      Self::CaseInsensitive => found.eq_ignore_ascii_case(name),
     }
-    // find next header line
-    if plaintext[i] == b'\r' && plaintext[i + 1] == b'\n' {
-      if current_header_name == name {
-        // Copy the header line (including CRLF)
-        result = plaintext[header_start_pos..i].to_vec();
-        break;
-      }
+  }
+}
 
-      // Check for end of headers (double CRLF)
-      if i + 3 < plaintext.len() && plaintext[i + 2] == b'\r' && plaintext[i + 3] == b'\n' {
-        break;
-      }
+pub fn compute_http_header_witness(plaintext: &[u8], name: &[u8]) -> (usize, Vec<u8>) {
+  compute_http_header_witness_matching(plaintext, name, HeaderNameMatch::Exact)
+}
 
-      current_header += 1;
-      header_start_pos = i + 2;
-    }
-  }
+/// Finds the first header line named `name`, comparing names as `name_match` says. The header
+/// lines are read as [`HttpMessage::parse_head`] does, so `plaintext` may end before the body.
+/// # Returns
+/// - the index of the header and its line exactly as in the plaintext, which is what the circuit
+///   digests
+/// - if there is no such header, the number of header lines read and an empty line
+pub fn compute_http_header_witness_matching(
+  plaintext: &[u8],
+  name: &[u8],
+  name_match: HeaderNameMatch,
+) -> (usize, Vec<u8>) {
+  let message = HttpMessage::parse_head(plaintext, LineEndings::Strict);
+  let found = message.headers_named(name, name_match).next();
+  found
+    .map_or((message.headers.len(), Vec::new()), |(idx, header)| (idx, header.line.bytes.to_vec()))
+}
 
-  (current_header, result)
+/// Finds every header line named `name`, e.g. each `set-cookie` of a response, comparing names as
+/// `name_match` says.
+/// # Returns
+/// - the index of each matching header and its line, without the CRLF, in plaintext order
+pub fn compute_http_header_witnesses(
+  plaintext: &[u8],
+  name: &[u8],
+  name_match: HeaderNameMatch,
+) -> Vec<(usize, Vec<u8>)> {
+  HttpMessage::parse_head(plaintext, LineEndings::Strict)
+    .headers_named(name, name_match)
+    .map(|(idx, header)| (idx, header.line.bytes.to_vec()))
+    .collect()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_http_machine_round_trip() {
+    for state in parser::parse(mock::RESPONSE_PLAINTEXT.as_bytes(), F::from(2)).unwrap() {
+      assert_eq!(HttpMachine::try_from(RawHttpMachine::from(state)).unwrap(), state);
+    }
+    let initial_state = HttpMachine::try_from(RawHttpMachine::initial_state()).unwrap();
+    assert_eq!(initial_state.status, HttpStatus::ParsingStart(StartLineLocation::Beginning));
+  }
+
+  #[rstest]
+  #[case::no_status(RawHttpMachine::default())]
+  #[case::start_not_a_counter(RawHttpMachine { parsing_start: -F::ONE, ..Default::default() })]
+  #[case::two_statuses(RawHttpMachine {
+    parsing_start: F::ONE,
+    parsing_body: F::ONE,
+    ..Default::default()
+  })]
+  #[case::name_and_value(RawHttpMachine {
+    parsing_header: F::ONE,
+    parsing_field_name: F::ONE,
+    parsing_field_value: F::ONE,
+    line_monomial: F::ONE,
+    ..Default::default()
+  })]
+  #[case::header_in_start_line(RawHttpMachine {
+    parsing_start: F::ONE,
+    parsing_header: F::ONE,
+    line_monomial: F::ONE,
+    ..Default::default()
+  })]
+  #[case::header_zero(RawHttpMachine {
+    parsing_field_name: F::ONE,
+    line_monomial: F::ONE,
+    ..Default::default()
+  })]
+  #[case::huge_header_counter(RawHttpMachine {
+    parsing_header: -F::ONE,
+    parsing_field_value: F::ONE,
+    line_monomial: F::ONE,
+    ..Default::default()
+  })]
+  #[case::digest_in_body(RawHttpMachine {
+    parsing_body: F::ONE,
+    line_digest: F::ONE,
+    ..Default::default()
+  })]
+  #[case::no_monomial_in_line(RawHttpMachine { parsing_start: F::from(2), ..Default::default() })]
+  fn test_http_machine_invalid(#[case] raw: RawHttpMachine) {
+    assert!(matches!(
+      HttpMachine::try_from(raw),
+      Err(WitnessGeneratorError::InvalidHttpMachine(_))
+    ));
+  }
+
+  #[test]
+  fn test_headers_keep_order() {
+    let mut headers = Headers::from([("b", "1"), ("a", "2"), ("c", "3")]);
+    assert_eq!(headers.insert("a", "4"), Some("2".to_string()));
+    assert_eq!(headers.get("a"), Some("4"));
+    assert_eq!(headers.len(), 3);
+    assert_eq!(headers_to_bytes(&headers).collect::<Vec<_>>(), [
+      b"b: 1".to_vec(),
+      b"a: 4".to_vec(),
+      b"c: 3".to_vec()
+    ]);
+
+    let from_map = Headers::from(HashMap::from([
+      ("b".to_string(), "1".to_string()),
+      ("a".to_string(), "2".to_string()),
+    ]));
+    assert_eq!(from_map, Headers::from([("a", "2"), ("b", "1")]));
+  }
+
+  #[test]
+  fn test_headers_multiple_values() {
+    let mut headers = Headers::from([("set-cookie", "a=1"), ("content-type", "text/plain")]);
+    headers.append("set-cookie", "b=2");
+    assert_eq!(headers.len(), 3);
+    assert_eq!(headers.get("set-cookie"), Some("a=1"));
+    assert_eq!(headers.get_all("set-cookie").collect::<Vec<_>>(), ["a=1", "b=2"]);
+    assert_eq!(headers_to_bytes(&headers).collect::<Vec<_>>(), [
+      b"set-cookie: a=1".to_vec(),
+      b"content-type: text/plain".to_vec(),
+      b"set-cookie: b=2".to_vec()
+    ]);
+    assert_eq!(
+      serde_json::to_value(&headers).unwrap(),
+      serde_json::json!({ "set-cookie": ["a=1", "b=2"], "content-type": ["text/plain"] })
+    );
+
+    // Inserting a repeated name keeps a single value, where the first one was
+    headers.append("set-cookie", "c=3");
+    assert_eq!(headers.insert("set-cookie", "d=4"), Some("a=1".to_string()));
+    assert_eq!(headers, Headers::from([("set-cookie", "d=4"), ("content-type", "text/plain")]));
+  }
+
+  #[test]
+  fn test_compute_http_header_witnesses() {
+    let plaintext = b"HTTP/1.1 200 OK\r\nset-cookie: a=1\r\ncontent-type: text/plain\r\n\
+                      set-cookie: b=2\r\n\r\nbody";
+    assert_eq!(compute_http_header_witnesses(plaintext, b"set-cookie", HeaderNameMatch::Exact), [
+      (0, b"set-cookie: a=1".to_vec()),
+      (2, b"set-cookie: b=2".to_vec())
+    ]);
+    assert!(compute_http_header_witnesses(plaintext, b"set", HeaderNameMatch::Exact).is_empty());
+  }
+
+  #[test]
+  fn test_split_header_line() {
+    assert_eq!(split_header_line(b"foo: bar"), Some((b"foo".as_slice(), b"bar".as_slice())));
+    assert_eq!(split_header_line(b"foo:bar"), Some((b"foo".as_slice(), b"bar".as_slice())));
+    assert_eq!(
+      split_header_line(b"foo:\t  bar baz \t"),
+      Some((b"foo".as_slice(), b"bar baz".as_slice()))
+    );
+    assert_eq!(split_header_line(b"foo:"), Some((b"foo".as_slice(), b"".as_slice())));
+    assert_eq!(split_header_line(b"foo"), None);
+  }
+
+  #[test]
+  fn test_plaintext_header_lines() {
+    let plaintext = b"HTTP/1.1 200 OK\r\nfoo:bar\r\nbaz:  \tqux\r\nx: y\r\n\r\n";
+    let headers = Headers::from([("baz", "qux"), ("Foo", "bar"), ("x", "y")]);
+    assert_eq!(plaintext_header_lines(plaintext, &headers).unwrap(), [
+      b"baz:  \tqux".to_vec(),
+      b"foo:bar".to_vec(),
+      b"x: y".to_vec(),
+    ]);
+
+    for headers in [Headers::from([("missing", "z")]), Headers::from([("x", "Y")])] {
+      assert!(matches!(
+        plaintext_header_lines(plaintext, &headers),
+        Err(WitnessGeneratorError::InvalidManifest(_))
+      ));
+    }
+  }
+
   #[test]
   fn test_compute_http_witness_start_line() {
     let bytes = compute_http_witness(RESPONSE_PLAINTEXT.as_bytes(), HttpMaskType::StartLine);
@@ -286,10 +655,91 @@ mod tests {
     assert_eq!(index, 2);
   }
 
+  #[test]
+  fn test_compute_http_witness_name_case_insensitive() {
+    let plaintext = RESPONSE_PLAINTEXT.as_bytes();
+    let (_, exact) = compute_http_header_witness(plaintext, b"Content-Type");
+    assert!(exact.is_empty());
+
+    let (index, bytes) = compute_http_header_witness_matching(
+      plaintext,
+      b"Content-Type",
+      HeaderNameMatch::CaseInsensitive,
+    );
+    assert_eq!(index, 0);
+    assert_eq!(bytes, RESPONSE_HEADER_0.as_bytes());
+  }
+
+  #[test]
+  fn test_headers_with_casing_of() {
+    let plaintext = b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nset-cookie: b=2\r\n\r\n";
+    let headers = Headers::from([("SET-COOKIE", "a=1"), ("Set-cookie", "b=2"), ("X-Missing", "c")]);
+    assert_eq!(
+      headers.with_casing_of(plaintext),
+      Headers::from([("Set-Cookie", "a=1"), ("set-cookie", "b=2"), ("X-Missing", "c")])
+    );
+
+    let manifest_headers = Headers::from([("Content-Type", "application/json; charset=utf-8")]);
+    let headers = manifest_headers.with_casing_of(RESPONSE_PLAINTEXT.as_bytes());
+    assert_eq!(headers_to_bytes(&headers).next().unwrap(), RESPONSE_HEADER_0.as_bytes());
+  }
+
+  #[test]
+  fn test_compute_http_witness_lf_only() {
+    let plaintext = RESPONSE_PLAINTEXT.replace("\r\n", "\n");
+    let witness = |mask_at, line_endings| {
+      compute_http_witness_with(plaintext.as_bytes(), mask_at, line_endings)
+    };
+    assert!(witness(HttpMaskType::Body, LineEndings::Strict).is_empty());
+    assert_eq!(
+      witness(HttpMaskType::StartLine, LineEndings::Lenient),
+      RESPONSE_START_LINE.as_bytes()
+    );
+    assert_eq!(
+      witness(HttpMaskType::Header(1), LineEndings::Lenient),
+      RESPONSE_HEADER_1.as_bytes()
+    );
+    assert_eq!(
+      witness(HttpMaskType::Body, LineEndings::Lenient),
+      RESPONSE_BODY.replace("\r\n", "\n").as_bytes()
+    );
+  }
+
   #[test]
   fn test_compute_http_witness_name_not_present() {
     let (_, bytes_from_name) =
       compute_http_header_witness(RESPONSE_PLAINTEXT.as_bytes(), "pluto-rocks".as_bytes());
     assert!(bytes_from_name.is_empty());
   }
+
+  /// A first ciphertext chunk ends before the body, its complete lines are still there
+  #[test]
+  fn test_compute_http_witness_partial_plaintext() {
+    let plaintext = &RESPONSE_PLAINTEXT.as_bytes()[..80];
+    assert_eq!(
+      compute_http_witness(plaintext, HttpMaskType::StartLine),
+      RESPONSE_START_LINE.as_bytes()
+    );
+    assert_eq!(
+      compute_http_witness(plaintext, HttpMaskType::Header(0)),
+      RESPONSE_HEADER_0.as_bytes()
+    );
+    assert!(compute_http_witness(plaintext, HttpMaskType::Header(1)).is_empty());
+    assert!(compute_http_witness(plaintext, HttpMaskType::Body).is_empty());
+
+    assert_eq!(
+      compute_http_header_witness(plaintext, b"content-type"),
+      (0, RESPONSE_HEADER_0.as_bytes().to_vec())
+    );
+    assert_eq!(compute_http_header_witness(plaintext, b"content-encoding"), (1, vec![]));
+  }
+
+  /// A missing header gives the number of header lines read, whether or not the message parses
+  #[rstest]
+  #[case::complete(RESPONSE_PLAINTEXT.as_bytes(), 3)]
+  #[case::malformed_line(b"HTTP/1.1 200 OK\r\na: b\r\nnot a header\r\n\r\n", 1)]
+  #[case::no_line(b"HTTP/1.1 200 OK", 0)]
+  fn test_compute_http_header_witness_not_found(#[case] plaintext: &[u8], #[case] index: usize) {
+    assert_eq!(compute_http_header_witness(plaintext, b"pluto-rocks"), (index, vec![]));
+  }
 }
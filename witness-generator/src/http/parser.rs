@@ -6,112 +6,273 @@ const LF: u8 = 10;
 const COLON: u8 = 58;
 
 pub fn parse(bytes: &[u8], polynomial_input: F) -> Result<Vec<HttpMachine>, WitnessGeneratorError> {
-  let mut machine = HttpMachine {
-    header_num:    0,
-    status:        HttpStatus::ParsingStart(StartLineLocation::Beginning),
-    line_digest:   F::ZERO,
-    line_monomial: F::ONE,
-  };
-
-  let mut output = vec![];
-  let mut line_ctr = 0;
-  for (_ctr, char) in bytes.iter().enumerate() {
-    // println!("-------------------------------------------------");
-    // println!("char: {:?}, {}", *char as char, *char);
-    // println!("-------------------------------------------------");
-    match (*char, machine.status) {
-      (SPACE, HttpStatus::ParsingStart(loc)) => {
-        match loc {
-          StartLineLocation::Beginning =>
-            machine.status = HttpStatus::ParsingStart(StartLineLocation::Middle),
-          StartLineLocation::Middle =>
-            machine.status = HttpStatus::ParsingStart(StartLineLocation::End),
-          StartLineLocation::End => {},
-        };
-        machine.line_digest += polynomial_input.pow([line_ctr]) * F::from(*char as u64);
-        line_ctr += 1;
-      },
-      (
-        CR,
-        HttpStatus::ParsingStart(StartLineLocation::End)
-        | HttpStatus::ParsingHeader(NameOrValue::Value),
-      ) => {
-        machine.status = HttpStatus::LineStatus(LineStatus::CR);
-        line_ctr = 0;
-        machine.line_digest = F::ZERO;
-      },
-      (CR, HttpStatus::LineStatus(LineStatus::CRLF)) => {
-        machine.status = HttpStatus::LineStatus(LineStatus::CRLFCR);
-        line_ctr = 0;
-        machine.line_digest = F::ZERO;
-      },
-      (LF, HttpStatus::LineStatus(LineStatus::CR)) => {
-        machine.status = HttpStatus::LineStatus(LineStatus::CRLF);
-        line_ctr = 0;
-        machine.line_digest = F::ZERO;
-      },
-      (LF, HttpStatus::LineStatus(LineStatus::CRLFCR)) => {
-        machine.status = HttpStatus::ParsingBody;
-        machine.header_num = 0;
-        line_ctr = 0;
-        machine.line_digest = F::ZERO;
-      },
-      (_, HttpStatus::LineStatus(LineStatus::CRLF)) => {
-        machine.status = HttpStatus::ParsingHeader(NameOrValue::Name);
-        machine.header_num += 1;
-        machine.line_digest += polynomial_input.pow([line_ctr]) * F::from(*char as u64);
-        line_ctr += 1;
-      },
-      (COLON, HttpStatus::ParsingHeader(NameOrValue::Name)) => {
-        machine.status = HttpStatus::ParsingHeader(NameOrValue::Value);
-        machine.line_digest += polynomial_input.pow([line_ctr]) * F::from(*char as u64);
-        line_ctr += 1;
-      },
-      (_, HttpStatus::ParsingBody) => {},
-      _ => {
-        machine.line_digest += polynomial_input.pow([line_ctr]) * F::from(*char as u64);
-        line_ctr += 1;
-      },
+  parse_with(bytes, polynomial_input, LineEndings::Strict)
+}
+
+/// [`parse`] accepting the given [`LineEndings`]. In [`LineEndings::Lenient`] mode a bare LF ends a
+/// line as CRLF does, so the states only match a circuit variant that accepts bare LF.
+pub fn parse_with(
+  bytes: &[u8],
+  polynomial_input: F,
+  line_endings: LineEndings,
+) -> Result<Vec<HttpMachine>, WitnessGeneratorError> {
+  StreamingParser::new(polynomial_input, line_endings).feed(bytes)
+}
+
+/// [`parse_with`] continuing from `machine_state`, e.g. the `machine_state` of a fold, instead of
+/// the start of the message. Offsets in errors are counted from the first byte of `bytes`.
+pub fn parse_from_checkpoint(
+  bytes: &[u8],
+  polynomial_input: F,
+  machine_state: RawHttpMachine,
+  line_endings: LineEndings,
+) -> Result<Vec<HttpMachine>, WitnessGeneratorError> {
+  StreamingParser::resume(machine_state, polynomial_input, line_endings)?.feed(bytes)
+}
+
+/// Machine states at the start of each `DATA_BYTES` chunk of `bytes`, i.e. the `machine_state` of
+/// every `HTTPVerification` fold, each with its digest `step_in[3]`.
+///
+/// The first fold starts from [`RawHttpMachine::initial_state`], every other fold from the
+/// [`StreamingParser::checkpoint`] after the last byte of the previous chunk.
+///
+/// `HTTPVerification` multiplies the first byte of a fold by `line_monomial`, which is zero right
+/// after a line break, so a header line starting a fold would be digested without its first byte.
+/// Such a boundary fails with [`WitnessGeneratorError::HeaderLineAtFoldBoundary`].
+pub fn parse_folds(
+  bytes: &[u8],
+  data_bytes: usize,
+  polynomial_input: F,
+) -> Result<Vec<(RawHttpMachine, F)>, WitnessGeneratorError> {
+  if data_bytes == 0 {
+    return Err(WitnessGeneratorError::CatchAll("data bytes must be positive".to_string()));
+  }
+  let mut parser = StreamingParser::new(polynomial_input, LineEndings::Strict);
+  let mut machine_states = Vec::with_capacity(bytes.len().div_ceil(data_bytes));
+  for chunk in bytes.chunks(data_bytes) {
+    let machine_state = parser.checkpoint();
+    if parser.machine().status == HttpStatus::LineStatus(LineStatus::CRLF) && chunk[0] != CR {
+      return Err(WitnessGeneratorError::HeaderLineAtFoldBoundary { offset: parser.offset() });
+    }
+    machine_states
+      .push((machine_state, field_polynomial_digest(&machine_state.flatten(), polynomial_input)));
+    parser.feed(chunk)?;
+  }
+  Ok(machine_states)
+}
+
+/// Incremental [`parse_with`] for a plaintext arriving in pieces, e.g. record by record over TLS.
+///
+/// Feeding the pieces one after the other gives the states [`parse_with`] gives for them
+/// concatenated, so witnesses can be computed while the rest of the plaintext is still arriving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamingParser {
+  machine:          HttpMachine,
+  /// Whether a byte of the current line was digested, `line_monomial` is then the power of
+  /// `polynomial_input` the next byte is multiplied by
+  line_started:     bool,
+  /// `parsing_start` of the circuit, one more than the spaces read in the start line. The status
+  /// stops counting at [`StartLineLocation::End`], the circuit does not.
+  parsing_start:    F,
+  polynomial_input: F,
+  line_endings:     LineEndings,
+  /// Number of bytes fed so far
+  offset:           usize,
+}
+
+impl StreamingParser {
+  pub fn new(polynomial_input: F, line_endings: LineEndings) -> Self {
+    let machine = HttpMachine {
+      header_num:    0,
+      status:        HttpStatus::ParsingStart(StartLineLocation::Beginning),
+      line_digest:   F::ZERO,
+      line_monomial: F::ONE,
+    };
+    Self {
+      machine,
+      line_started: false,
+      parsing_start: F::ONE,
+      polynomial_input,
+      line_endings,
+      offset: 0,
+    }
+  }
+
+  /// Continues from `checkpoint`, e.g. from [`StreamingParser::checkpoint`] or the `machine_state`
+  /// of a fold. Offsets in errors are counted from the checkpoint.
+  ///
+  /// The position in the current line is read from the state: a zero `line_monomial` is the start
+  /// of a line, as is the initial state, otherwise the next byte is multiplied by `line_monomial`.
+  pub fn resume(
+    checkpoint: RawHttpMachine,
+    polynomial_input: F,
+    line_endings: LineEndings,
+  ) -> Result<Self, WitnessGeneratorError> {
+    let machine = HttpMachine::try_from(checkpoint)?;
+    let initial_state = machine.status == HttpStatus::ParsingStart(StartLineLocation::Beginning)
+      && machine.line_monomial == F::ONE
+      && machine.line_digest == F::ZERO;
+    let line_started = machine.line_monomial != F::ZERO && !initial_state;
+    Ok(Self {
+      machine,
+      line_started,
+      parsing_start: checkpoint.parsing_start,
+      polynomial_input,
+      line_endings,
+      offset: 0,
+    })
+  }
+
+  /// State after the last byte fed
+  pub fn machine(&self) -> HttpMachine { self.machine }
+
+  /// State after the last byte fed, as the `machine_state` of a fold starting at the next byte
+  pub fn checkpoint(&self) -> RawHttpMachine {
+    match self.machine.status {
+      HttpStatus::ParsingStart(_) =>
+        RawHttpMachine { parsing_start: self.parsing_start, ..RawHttpMachine::from(self.machine) },
+      _ => RawHttpMachine::from(self.machine),
     }
-    machine.line_monomial = if line_ctr == 0 { F::ZERO } else { polynomial_input.pow([line_ctr]) };
-    output.push(machine);
-    // let raw_state = RawHttpMachine::from(machine);
-
-    // println!(
-    //   "state[ {ctr:?} ].parsing_start       = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.parsing_start.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].parsing_header      = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.parsing_header.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].parsing_field_name  = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.parsing_field_name.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].parsing_field_value = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.parsing_field_value.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].parsing_body        = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.parsing_body.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].line_status         = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.line_status.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].inner_main_digest   = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.line_digest.to_bytes())
-    // );
-    // println!(
-    //   "state[ {ctr:?} ].line_monomial       = {:?}",
-    //   BigUint::from_bytes_le(&raw_state.line_monomial.to_bytes())
-    // );
-    // println!("xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx");
-  }
-  Ok(output)
+  }
+
+  /// Number of bytes fed so far
+  pub fn offset(&self) -> usize { self.offset }
+
+  /// Parses the next `bytes`, returning the state after each of them.
+  ///
+  /// Fails with [`WitnessGeneratorError::HttpParser`] at the first byte `HTTPVerification` would
+  /// not parse as HTTP, with its offset counted from the first byte ever fed and the parser status
+  /// before it:
+  /// - a bare LF, or a CR not followed by LF
+  /// - a start line that does not have its three parts, or starts with a space
+  /// - a header line without a colon, or with an empty name
+  ///
+  /// A failed feed leaves the parser as it was.
+  pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<HttpMachine>, WitnessGeneratorError> {
+    let Self {
+      mut machine,
+      mut line_started,
+      mut parsing_start,
+      polynomial_input,
+      line_endings,
+      offset,
+    } = *self;
+    // Power of `polynomial_input` the next digested byte is multiplied by
+    let mut monomial = if line_started { machine.line_monomial } else { F::ONE };
+    let lenient = line_endings == LineEndings::Lenient;
+    let mut output = vec![];
+    for (ctr, char) in bytes.iter().enumerate() {
+      let malformed = match (*char, machine.status) {
+        (_, HttpStatus::ParsingBody) => false,
+        (LF, HttpStatus::LineStatus(LineStatus::CR | LineStatus::CRLFCR)) => false,
+        (
+          LF,
+          HttpStatus::ParsingStart(StartLineLocation::End)
+          | HttpStatus::ParsingHeader(NameOrValue::Value)
+          | HttpStatus::LineStatus(LineStatus::CRLF),
+        ) if lenient => false,
+        (LF, _) | (_, HttpStatus::LineStatus(LineStatus::CR | LineStatus::CRLFCR)) => true,
+        (
+          CR,
+          HttpStatus::ParsingStart(StartLineLocation::Beginning | StartLineLocation::Middle)
+          | HttpStatus::ParsingHeader(NameOrValue::Name),
+        ) => true,
+        (SPACE, HttpStatus::ParsingStart(StartLineLocation::Beginning)) => !line_started,
+        (COLON, HttpStatus::LineStatus(LineStatus::CRLF)) => true,
+        _ => false,
+      };
+      if malformed {
+        return Err(WitnessGeneratorError::HttpParser {
+          offset: offset + ctr,
+          status: machine.status,
+        });
+      }
+      match (*char, machine.status) {
+        // A bare LF ends a line, or the headers after an empty line
+        (LF, HttpStatus::ParsingStart(_) | HttpStatus::ParsingHeader(_)) if lenient => {
+          machine.status = HttpStatus::LineStatus(LineStatus::CRLF);
+          line_started = false;
+          monomial = F::ONE;
+          machine.line_digest = F::ZERO;
+        },
+        (LF, HttpStatus::LineStatus(LineStatus::CRLF)) if lenient => {
+          machine.status = HttpStatus::ParsingBody;
+          machine.header_num = 0;
+          line_started = false;
+          monomial = F::ONE;
+          machine.line_digest = F::ZERO;
+        },
+        (SPACE, HttpStatus::ParsingStart(loc)) => {
+          match loc {
+            StartLineLocation::Beginning =>
+              machine.status = HttpStatus::ParsingStart(StartLineLocation::Middle),
+            StartLineLocation::Middle =>
+              machine.status = HttpStatus::ParsingStart(StartLineLocation::End),
+            StartLineLocation::End => {},
+          };
+          parsing_start += F::ONE;
+          machine.line_digest += monomial * F::from(*char as u64);
+          monomial *= polynomial_input;
+          line_started = true;
+        },
+        (
+          CR,
+          HttpStatus::ParsingStart(StartLineLocation::End)
+          | HttpStatus::ParsingHeader(NameOrValue::Value),
+        ) => {
+          machine.status = HttpStatus::LineStatus(LineStatus::CR);
+          line_started = false;
+          monomial = F::ONE;
+          machine.line_digest = F::ZERO;
+        },
+        (CR, HttpStatus::LineStatus(LineStatus::CRLF)) => {
+          machine.status = HttpStatus::LineStatus(LineStatus::CRLFCR);
+          line_started = false;
+          monomial = F::ONE;
+          machine.line_digest = F::ZERO;
+        },
+        (LF, HttpStatus::LineStatus(LineStatus::CR)) => {
+          machine.status = HttpStatus::LineStatus(LineStatus::CRLF);
+          line_started = false;
+          monomial = F::ONE;
+          machine.line_digest = F::ZERO;
+        },
+        (LF, HttpStatus::LineStatus(LineStatus::CRLFCR)) => {
+          machine.status = HttpStatus::ParsingBody;
+          machine.header_num = 0;
+          line_started = false;
+          monomial = F::ONE;
+          machine.line_digest = F::ZERO;
+        },
+        (_, HttpStatus::LineStatus(LineStatus::CRLF)) => {
+          machine.status = HttpStatus::ParsingHeader(NameOrValue::Name);
+          machine.header_num += 1;
+          machine.line_digest += monomial * F::from(*char as u64);
+          monomial *= polynomial_input;
+          line_started = true;
+        },
+        (COLON, HttpStatus::ParsingHeader(NameOrValue::Name)) => {
+          machine.status = HttpStatus::ParsingHeader(NameOrValue::Value);
+          machine.line_digest += monomial * F::from(*char as u64);
+          monomial *= polynomial_input;
+          line_started = true;
+        },
+        (_, HttpStatus::ParsingBody) => {},
+        _ => {
+          machine.line_digest += monomial * F::from(*char as u64);
+          monomial *= polynomial_input;
+          line_started = true;
+        },
+      }
+      machine.line_monomial = if line_started { monomial } else { F::ZERO };
+      output.push(machine);
+    }
+    self.machine = machine;
+    self.line_started = line_started;
+    self.parsing_start = parsing_start;
+    self.offset += bytes.len();
+    Ok(output)
+  }
 }
 
 #[cfg(test)]
@@ -136,6 +297,235 @@ mod tests {
     assert_eq!(machine_state.line_monomial, F::from(0));
   }
 
+  #[test]
+  fn test_parse_folds() {
+    let polynomial_input = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let plaintext = mock::RESPONSE_PLAINTEXT.as_bytes();
+    let states = parse(plaintext, polynomial_input).unwrap();
+    let folds = parse_folds(plaintext, 128, polynomial_input).unwrap();
+
+    assert_eq!(folds.len(), 3);
+    assert_eq!(folds[0], (RawHttpMachine::initial_state(), F::ONE + polynomial_input.pow([7])));
+    assert_eq!(folds[1].0, RawHttpMachine::from(states[127]));
+    assert_eq!(folds[2].0, RawHttpMachine::from(states[255]));
+    for (machine_state, digest) in folds {
+      assert_eq!(digest, field_polynomial_digest(&machine_state.flatten(), polynomial_input));
+    }
+
+    assert_eq!(parse_folds(plaintext, 512, polynomial_input).unwrap().len(), 1);
+    assert_eq!(parse_folds(&plaintext[..256], 128, polynomial_input).unwrap().len(), 2);
+    assert!(parse_folds(plaintext, 0, polynomial_input).is_err());
+  }
+
+  #[test]
+  fn test_parse_folds_reason_phrase_spaces() {
+    let polynomial_input = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let plaintext = b"HTTP/1.1 404 Not Found\r\n\r\n";
+    let folds = parse_folds(plaintext, "HTTP/1.1 404 Not ".len(), polynomial_input).unwrap();
+    assert_eq!(folds[1].0.parsing_start, F::from(4));
+  }
+
+  #[rstest]
+  #[case::header_line(17, Err(17))]
+  #[case::empty_line(24, Ok(2))]
+  #[case::mid_line(18, Ok(2))]
+  fn test_parse_folds_line_start(
+    #[case] data_bytes: usize,
+    #[case] expected: Result<usize, usize>,
+  ) {
+    let polynomial_input = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let plaintext = b"HTTP/1.1 200 OK\r\na: b\r\n\r\nhi";
+    let folds = parse_folds(plaintext, data_bytes, polynomial_input);
+    match expected {
+      Ok(len) => assert_eq!(folds.unwrap().len(), len),
+      Err(offset) => assert!(matches!(
+        folds,
+        Err(WitnessGeneratorError::HeaderLineAtFoldBoundary { offset: err }) if err == offset
+      )),
+    }
+  }
+
+  #[rstest]
+  #[case::byte_by_byte(1)]
+  #[case::records(37)]
+  #[case::mid_line(100)]
+  #[case::whole(512)]
+  fn test_streaming_parser(#[case] record_len: usize) {
+    let polynomial_input = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let plaintext = mock::RESPONSE_PLAINTEXT.as_bytes();
+    let states = parse(plaintext, polynomial_input).unwrap();
+
+    let mut parser = StreamingParser::new(polynomial_input, LineEndings::Strict);
+    let mut streamed = vec![];
+    for record in plaintext.chunks(record_len) {
+      streamed.extend(parser.feed(record).unwrap());
+      assert_eq!(parser.machine(), streamed[streamed.len() - 1]);
+      assert_eq!(parser.checkpoint(), RawHttpMachine::from(parser.machine()));
+    }
+    assert_eq!(streamed, states);
+    assert_eq!(parser.offset(), plaintext.len());
+  }
+
+  #[test]
+  fn test_streaming_parser_error() {
+    let mut parser = StreamingParser::new(F::from(2), LineEndings::Strict);
+    parser.feed(b"HTTP/1.1 200 OK\r\n").unwrap();
+    let before = parser;
+    assert!(matches!(
+      parser.feed(b"foo\r\n"),
+      Err(WitnessGeneratorError::HttpParser {
+        offset: 20,
+        status: HttpStatus::ParsingHeader(NameOrValue::Name),
+      })
+    ));
+    assert_eq!(parser, before);
+    parser.feed(b"foo: bar\r\n\r\n").unwrap();
+    assert_eq!(parser.machine().status, HttpStatus::ParsingBody);
+  }
+
+  #[test]
+  fn test_parse_from_checkpoint() {
+    let polynomial_input = poseidon::<2>(&[F::from(69), F::from(420)]);
+    let plaintext = mock::RESPONSE_PLAINTEXT.as_bytes();
+    let states = parse(plaintext, polynomial_input).unwrap();
+
+    // Every chunk resumes from the checkpoint after the previous one, mid-line or at a line start
+    let mut parser = StreamingParser::new(polynomial_input, LineEndings::Strict);
+    for (idx, chunk) in plaintext.chunks(64).enumerate() {
+      let resumed = parse_from_checkpoint(
+        &plaintext[idx * 64..],
+        polynomial_input,
+        parser.checkpoint(),
+        LineEndings::Strict,
+      )
+      .unwrap();
+      assert_eq!(resumed, states[idx * 64..]);
+      parser.feed(chunk).unwrap();
+    }
+
+    // The initial state and a line start resume at the start of a line
+    for machine_state in [RawHttpMachine::initial_state(), RawHttpMachine::from(states[16])] {
+      let parser =
+        StreamingParser::resume(machine_state, polynomial_input, LineEndings::Strict).unwrap();
+      assert!(!parser.line_started);
+    }
+    let parser = StreamingParser::resume(
+      RawHttpMachine::from(states[20]),
+      polynomial_input,
+      LineEndings::Strict,
+    )
+    .unwrap();
+    assert!(parser.line_started);
+  }
+
+  /// The circuit counts every space of the start line, so a checkpoint in a reason phrase with
+  /// spaces has `parsing_start` past 3
+  #[test]
+  fn test_parse_from_checkpoint_in_reason_phrase() {
+    let polynomial_input = F::from(7);
+    let plaintext = b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n";
+    let states = parse(plaintext, polynomial_input).unwrap();
+
+    // After `HTTP/1.1 404 Not `
+    let mut machine_state = RawHttpMachine::from(states[16]);
+    assert_eq!(machine_state.parsing_start, F::from(3));
+    machine_state.parsing_start = F::from(4);
+    let resumed =
+      parse_from_checkpoint(&plaintext[17..], polynomial_input, machine_state, LineEndings::Strict)
+        .unwrap();
+    assert_eq!(resumed, states[17..]);
+  }
+
+  #[rstest]
+  #[case::bare_lf(b"HTTP/1.1 200 OK\n", 15, HttpStatus::ParsingStart(StartLineLocation::End))]
+  #[case::bare_cr(b"HTTP/1.1 200 OK\rX", 16, HttpStatus::LineStatus(LineStatus::CR))]
+  #[case::missing_start_line(
+    b"\r\n\r\n",
+    0,
+    HttpStatus::ParsingStart(StartLineLocation::Beginning)
+  )]
+  #[case::leading_space(
+    b" HTTP/1.1 200 OK",
+    0,
+    HttpStatus::ParsingStart(StartLineLocation::Beginning)
+  )]
+  #[case::short_start_line(
+    b"HTTP/1.1 200\r\n",
+    12,
+    HttpStatus::ParsingStart(StartLineLocation::Middle)
+  )]
+  #[case::missing_colon(
+    b"HTTP/1.1 200 OK\r\nfoo\r\n",
+    20,
+    HttpStatus::ParsingHeader(NameOrValue::Name)
+  )]
+  #[case::empty_name(b"HTTP/1.1 200 OK\r\n: foo\r\n", 17, HttpStatus::LineStatus(LineStatus::CRLF))]
+  #[case::lf_in_header(
+    b"HTTP/1.1 200 OK\r\na: b\n\r\n",
+    21,
+    HttpStatus::ParsingHeader(NameOrValue::Value)
+  )]
+  #[case::cr_after_headers(
+    b"HTTP/1.1 200 OK\r\n\r\r",
+    18,
+    HttpStatus::LineStatus(LineStatus::CRLFCR)
+  )]
+  fn test_parse_http_malformed(
+    #[case] bytes: &[u8],
+    #[case] offset: usize,
+    #[case] status: HttpStatus,
+  ) {
+    let result = parse(bytes, F::from(2));
+    assert!(
+      matches!(result, Err(WitnessGeneratorError::HttpParser { offset: o, status: s }) if o == offset && s == status),
+      "{result:?}"
+    );
+  }
+
+  /// The parser enters the body right where [`compute_http_witness_with`] finds it
+  #[rstest]
+  #[case::crlf_strict(mock::RESPONSE_PLAINTEXT.as_bytes().to_vec(), LineEndings::Strict)]
+  #[case::crlf_lenient(mock::RESPONSE_PLAINTEXT.as_bytes().to_vec(), LineEndings::Lenient)]
+  #[case::lf_lenient(mock::RESPONSE_PLAINTEXT.replace("\r\n", "\n").into_bytes(), LineEndings::Lenient)]
+  #[case::mixed_lenient(b"GET / HTTP/1.1\r\na: b\nc: d\r\n\nbody".to_vec(), LineEndings::Lenient)]
+  #[case::github_lenient(std::fs::read("../examples/http/github_response.http").unwrap(), LineEndings::Lenient)]
+  #[case::reddit_lenient(std::fs::read("../examples/http/reddit_request.http").unwrap(), LineEndings::Lenient)]
+  fn test_parse_http_witness_parity(#[case] input: Vec<u8>, #[case] line_endings: LineEndings) {
+    let states = parse_with(&input, F::from(2), line_endings).unwrap();
+    let body_start =
+      states.iter().position(|state| state.status == HttpStatus::ParsingBody).unwrap() + 1;
+    let body = compute_http_witness_with(&input, HttpMaskType::Body, line_endings);
+    assert_eq!(&input[body_start..], body);
+
+    // Every header line ends in `LineStatus::CRLF` with an empty line digest
+    let message = message::HttpMessage::parse_with(&input, line_endings).unwrap();
+    for header in &message.headers {
+      let state = states[header.line.range.start - 1];
+      assert_eq!(state.status, HttpStatus::LineStatus(LineStatus::CRLF));
+      assert_eq!(state.line_digest, F::ZERO);
+    }
+    assert_eq!(
+      states
+        .iter()
+        .filter(|state| state.status == HttpStatus::LineStatus(LineStatus::CRLF))
+        .count(),
+      message.headers.len() + 1
+    );
+  }
+
+  #[test]
+  fn test_parse_http_lf_only_strict() {
+    let input = mock::RESPONSE_PLAINTEXT.replace("\r\n", "\n");
+    assert!(parse(input.as_bytes(), F::from(2)).is_err());
+    assert!(compute_http_witness(input.as_bytes(), HttpMaskType::Body).is_empty());
+  }
+
+  #[test]
+  fn test_parse_http_body_is_not_checked() {
+    let states = parse(b"HTTP/1.1 200 OK\r\n\r\n\n\r: \r", F::from(2)).unwrap();
+    assert_eq!(states.last().unwrap().status, HttpStatus::ParsingBody);
+  }
+
   #[rstest]
   #[case::github("github_response")]
   #[case::reddit("reddit_request")]
@@ -143,8 +533,10 @@ mod tests {
     // It's funny to me every time
     let polynomial_input = poseidon::<2>(&[F::from(69), F::from(420)]);
 
+    // These fixtures have bare LF line endings
     let input = std::fs::read(format!("../examples/http/{}.http", filename)).unwrap();
-    let states = parse(&input, polynomial_input).unwrap();
+    assert!(parse(&input, polynomial_input).is_err());
+    let states = parse_with(&input, polynomial_input, LineEndings::Lenient).unwrap();
 
     let machine_state: [String; 8] = RawHttpMachine::from(states[511].to_owned()).into();
     dbg!(machine_state);
@@ -0,0 +1,427 @@
+//! Circom inputs of every fold, keyed by the signal names of the circuits.
+
+use std::{fs, path::Path};
+
+use serde::Serializer;
+
+use super::*;
+use crate::{
+  chacha20::{self, ChaCha20Input},
+  circuits::{
+    http_verification::http_verification, json_extraction::json_extraction,
+    plaintext_authentication::plaintext_authentication,
+  },
+  digest::initial_digest,
+  http::{
+    message::{HttpMessage, HttpMessageError},
+    RawHttpMachine,
+  },
+  json::{json_value_digest, RawJsonMachine},
+  manifest::Manifest,
+  public_io::PublicIo,
+  rom::{plan_rom, CircuitType},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaintextAuthenticationInput {
+  pub step_in:           PublicIo,
+  pub plaintext:         Vec<ByteOrPad>,
+  #[serde(flatten)]
+  pub chacha20:          ChaCha20Input,
+  #[serde(serialize_with = "serialize_field_element")]
+  pub ciphertext_digest: F,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpVerificationInput {
+  pub step_in:           PublicIo,
+  #[serde(serialize_with = "serialize_field_element")]
+  pub ciphertext_digest: F,
+  pub machine_state:     RawHttpMachine,
+  pub data:              Vec<ByteOrPad>,
+  #[serde(serialize_with = "serialize_field_elements")]
+  pub main_digests:      Vec<F>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonExtractionInput {
+  pub step_in:           PublicIo,
+  #[serde(serialize_with = "serialize_field_element")]
+  pub ciphertext_digest: F,
+  pub data:              Vec<ByteOrPad>,
+  #[serde(serialize_with = "serialize_field_element")]
+  pub sequence_digest:   F,
+  #[serde(serialize_with = "serialize_field_element")]
+  pub value_digest:      F,
+  /// [`RawJsonMachine::flatten`] of the parser state at the start of `data`
+  #[serde(serialize_with = "serialize_field_elements")]
+  pub state:             Vec<F>,
+}
+
+/// Inputs of a single fold
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum CircuitInput {
+  PlaintextAuthentication(PlaintextAuthenticationInput),
+  HttpVerification(HttpVerificationInput),
+  JsonExtraction(JsonExtractionInput),
+}
+
+impl CircuitInput {
+  pub fn circuit(&self) -> CircuitType {
+    match self {
+      Self::PlaintextAuthentication(_) => CircuitType::PlaintextAuthentication,
+      Self::HttpVerification(_) => CircuitType::HttpVerification,
+      Self::JsonExtraction(_) => CircuitType::JsonExtraction,
+    }
+  }
+}
+
+fn serialize_field_element<S>(fe: &F, serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer {
+  serializer.serialize_str(&field_element_to_base10_string(*fe))
+}
+
+fn serialize_field_elements<S>(fes: &[F], serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer {
+  serializer.collect_seq(fes.iter().map(|fe| field_element_to_base10_string(*fe)))
+}
+
+/// Computes the inputs of every fold proving a response, in ROM order.
+///
+/// The response is taken to be a single TLS record, encrypted with ChaCha20 under `key` and
+/// `nonce` starting at block `counter`. Every `step_in` is the `step_out` of the previous fold as
+/// computed by the [`circuits`](crate::circuits) models, so this fails if the run would not verify.
+/// The manifest is checked with [`Manifest::validate`] first. Chunked bodies fail with
+/// [`HttpMessageError::ChunkedBody`]: the circuits digest the chunk framing along with the JSON, so
+/// the extracted value cannot be located in the body as sent. A fold starting right at a header
+/// line fails with [`WitnessGeneratorError::HeaderLineAtFoldBoundary`], see
+/// [`parse_folds`](http::parser::parse_folds).
+///
+/// # Arguments
+/// - `manifest`: the statements about the response to prove
+/// - `plaintext`: the response plaintext
+/// - `key`, `nonce`, `counter`: the ChaCha20 parameters of the response
+/// - `data_bytes`: `DATA_BYTES` of the circuits
+/// - `max_rom_length`: the max ROM length the setup was made with
+pub fn fold_inputs<const MAX_STACK_HEIGHT: usize>(
+  manifest: &Manifest,
+  plaintext: &[u8],
+  key: &[u8; 32],
+  nonce: &[u8; 12],
+  counter: u32,
+  data_bytes: usize,
+  max_rom_length: usize,
+) -> Result<Vec<CircuitInput>, WitnessGeneratorError>
+where
+  [(); MAX_STACK_HEIGHT * 4 + 4]:,
+{
+  manifest.validate()?;
+  let message = HttpMessage::parse(plaintext)?;
+  // `HTTPVerification` and `JSONExtraction` digest the body as sent, chunk framing included
+  if message.is_chunked() {
+    return Err(HttpMessageError::ChunkedBody { offset: message.body.range.start }.into());
+  }
+  message.validate_content_length()?;
+  let body_offset = message.body.range.start;
+  let ciphertext = chacha20::encrypt(key, nonce, counter, plaintext);
+  let rom = plan_rom(plaintext.len(), ciphertext.len(), body_offset, data_bytes, max_rom_length)?;
+
+  let ciphertext_chunks = rom
+    .iter()
+    .filter(|step| step.circuit == CircuitType::PlaintextAuthentication)
+    .map(|step| step.padded_chunk(&ciphertext))
+    .collect::<Vec<_>>();
+  let (ciphertext_digest, mut step_in, main_digests) =
+    initial_digest::<MAX_STACK_HEIGHT>(manifest, plaintext, &ciphertext_chunks)?;
+  let sequence_digest = RawJsonMachine::<MAX_STACK_HEIGHT>::from_chosen_sequence_and_input(
+    ciphertext_digest,
+    &manifest.response.body.json,
+  )?
+  .compress_tree_hash();
+  let value =
+    json_value_digest::<MAX_STACK_HEIGHT>(&plaintext[body_offset..], &manifest.response.body.json)?;
+  let value_digest = polynomial_digest(&value, ciphertext_digest, 0);
+
+  let mut http_machine_states =
+    http::parser::parse_folds(plaintext, data_bytes, ciphertext_digest)?.into_iter();
+  let mut json_states = json::parser::parse_folds::<MAX_STACK_HEIGHT>(
+    &plaintext[body_offset..],
+    data_bytes,
+    ciphertext_digest,
+  )?
+  .into_iter();
+  let mut inputs = Vec::with_capacity(rom.len());
+  let mut plaintext_fold = 0;
+  for step in rom {
+    let data = step.padded_chunk(plaintext);
+    let fold_step_in = step_in;
+    let input = match step.circuit {
+      CircuitType::PlaintextAuthentication => {
+        let fold_counter = chacha20::fold_counter(counter, data_bytes, plaintext_fold)?;
+        step_in = plaintext_authentication(
+          step_in,
+          &data,
+          &ciphertext_chunks[plaintext_fold],
+          ciphertext_digest,
+        )?;
+        plaintext_fold += 1;
+        CircuitInput::PlaintextAuthentication(PlaintextAuthenticationInput {
+          step_in: fold_step_in,
+          plaintext: data,
+          chacha20: ChaCha20Input::new(key, nonce, fold_counter),
+          ciphertext_digest,
+        })
+      },
+      CircuitType::HttpVerification => {
+        let (machine_state, _) = http_machine_states.next().ok_or_else(|| {
+          WitnessGeneratorError::CatchAll("missing http machine state".to_string())
+        })?;
+        (step_in, _) =
+          http_verification(step_in, machine_state, &data, &main_digests, ciphertext_digest)?;
+        CircuitInput::HttpVerification(HttpVerificationInput {
+          step_in: fold_step_in,
+          ciphertext_digest,
+          machine_state,
+          data,
+          main_digests: main_digests.to_vec(),
+        })
+      },
+      CircuitType::JsonExtraction => {
+        let (state, _) = json_states.next().ok_or_else(|| {
+          WitnessGeneratorError::CatchAll("missing json machine state".to_string())
+        })?;
+        (step_in, _) = json_extraction(
+          step_in,
+          RawJsonMachine::unflatten(ciphertext_digest, &state),
+          &data,
+          sequence_digest,
+          value_digest,
+          ciphertext_digest,
+        )?;
+        CircuitInput::JsonExtraction(JsonExtractionInput {
+          step_in: fold_step_in,
+          ciphertext_digest,
+          data,
+          sequence_digest,
+          value_digest,
+          state: state.to_vec(),
+        })
+      },
+    };
+    inputs.push(input);
+  }
+  Ok(inputs)
+}
+
+/// Writes the inputs of every fold to `dir` as `{fold}_{circuit}.json`, e.g.
+/// `0_plaintext_authentication.json`.
+pub fn write_fold_inputs(dir: &Path, inputs: &[CircuitInput]) -> Result<(), WitnessGeneratorError> {
+  fs::create_dir_all(dir)?;
+  for (fold, input) in inputs.iter().enumerate() {
+    let path = dir.join(format!("{fold}_{}.json", input.circuit().name()));
+    fs::write(path, serde_json::to_string_pretty(input)?)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::http::Headers;
+
+  const DATA_BYTES: usize = 128;
+  const MAX_STACK_HEIGHT: usize = 10;
+  const NONCE: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+
+  /// [`RESPONSE_PLAINTEXT`] without its `Transfer-Encoding` header, as its body is not chunked
+  fn plaintext() -> String { RESPONSE_PLAINTEXT.replace("Transfer-Encoding: chunked\r\n", "") }
+
+  fn inputs() -> Vec<CircuitInput> {
+    fold_inputs::<MAX_STACK_HEIGHT>(
+      &mock_manifest(),
+      plaintext().as_bytes(),
+      &[0; 32],
+      &NONCE,
+      1,
+      DATA_BYTES,
+      10,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_fold_inputs() {
+    let inputs = inputs();
+    let circuits = inputs.iter().map(|input| input.circuit().index()).collect::<Vec<_>>();
+    assert_eq!(circuits, [0, 0, 0, 1, 1, 1, 2, 2]);
+
+    let CircuitInput::PlaintextAuthentication(first) = &inputs[0] else { panic!() };
+    assert_eq!(first.step_in.accumulator, first.ciphertext_digest);
+    assert_eq!(first.chacha20, ChaCha20Input::new(&[0; 32], &NONCE, 1));
+    let CircuitInput::PlaintextAuthentication(third) = &inputs[2] else { panic!() };
+    assert_eq!(third.chacha20.counter, chacha20::word_bits(5));
+
+    let CircuitInput::HttpVerification(http) = &inputs[3] else { panic!() };
+    assert_eq!(http.machine_state, RawHttpMachine::initial_state());
+    assert_eq!(http.main_digests.len(), MAX_NUMBER_OF_HEADERS + 1);
+    assert_eq!(
+      http.step_in.http_machine_state_digest,
+      field_polynomial_digest(&http.machine_state.flatten(), http.ciphertext_digest)
+    );
+
+    let CircuitInput::JsonExtraction(json) = &inputs[7] else { panic!() };
+    assert_ne!(json.step_in.json_machine_state_digest, F::ZERO);
+    assert_eq!(
+      json.step_in.json_machine_state_digest,
+      field_polynomial_digest(&json.state, json.ciphertext_digest)
+    );
+  }
+
+  #[test]
+  fn test_fold_inputs_json() {
+    let inputs = inputs();
+    let CircuitInput::PlaintextAuthentication(first) = &inputs[0] else { panic!() };
+    let json = serde_json::to_value(&inputs[0]).unwrap();
+    assert_eq!(json["step_in"].as_array().unwrap().len(), PUBLIC_IO_LENGTH);
+    assert_eq!(json["plaintext"].as_array().unwrap().len(), DATA_BYTES);
+    assert_eq!(json["key"].as_array().unwrap().len(), 8);
+    assert_eq!(json["nonce"].as_array().unwrap().len(), 3);
+    assert_eq!(json["counter"].as_array().unwrap().len(), 32);
+    assert_eq!(json["ciphertext_digest"], field_element_to_base10_string(first.ciphertext_digest));
+
+    let json = serde_json::to_value(&inputs[7]).unwrap();
+    assert_eq!(json["state"].as_array().unwrap().len(), MAX_STACK_HEIGHT * 4 + 4);
+    assert!(json["sequence_digest"].is_string());
+    assert!(json["value_digest"].is_string());
+  }
+
+  #[test]
+  fn test_fold_inputs_repeated_header() {
+    let plaintext =
+      format!("HTTP/1.1 200 OK\r\nset-cookie: a=1\r\nset-cookie: b=2\r\n\r\n{RESPONSE_BODY}");
+    let mut manifest = mock_manifest();
+    manifest.response.headers = Headers::from([("set-cookie", "a=1"), ("set-cookie", "b=2")]);
+
+    let inputs =
+      fold_inputs::<MAX_STACK_HEIGHT>(&manifest, plaintext.as_bytes(), &[0; 32], &NONCE, 1, 512, 3)
+        .unwrap();
+    let CircuitInput::PlaintextAuthentication(first) = &inputs[0] else { panic!() };
+    assert_eq!(first.step_in.num_matches, F::from(3));
+    // Every header line, including both cookies, is matched by `HTTPVerification`
+    let CircuitInput::JsonExtraction(json) = &inputs[2] else { panic!() };
+    assert_eq!(json.step_in.num_matches, F::ZERO);
+  }
+
+  #[test]
+  fn test_fold_inputs_header_whitespace() {
+    let plaintext = format!("HTTP/1.1 200 OK\r\nfoo:bar\r\nbaz: \t qux\r\n\r\n{RESPONSE_BODY}");
+    let mut manifest = mock_manifest();
+    manifest.response.headers = Headers::from([("foo", "bar"), ("baz", "qux")]);
+
+    let inputs =
+      fold_inputs::<MAX_STACK_HEIGHT>(&manifest, plaintext.as_bytes(), &[0; 32], &NONCE, 1, 512, 3)
+        .unwrap();
+    let CircuitInput::JsonExtraction(json) = &inputs[2] else { panic!() };
+    assert_eq!(json.step_in.num_matches, F::ZERO);
+  }
+
+  #[test]
+  fn test_fold_inputs_empty_json_sequence() {
+    let mut manifest = mock_manifest();
+    manifest.response.body.json = vec![];
+    assert!(matches!(
+      fold_inputs::<MAX_STACK_HEIGHT>(
+        &manifest,
+        plaintext().as_bytes(),
+        &[0; 32],
+        &NONCE,
+        1,
+        512,
+        3
+      ),
+      Err(WitnessGeneratorError::InvalidManifest(_))
+    ));
+  }
+
+  #[test]
+  fn test_fold_inputs_chunked_body() {
+    let plaintext = format!(
+      "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n{:x}\r\n{RESPONSE_BODY}\r\n0\r\n\r\n",
+      RESPONSE_BODY.len()
+    );
+    let message = HttpMessage::parse(plaintext.as_bytes()).unwrap();
+    assert_eq!(message.decoded_body().unwrap().bytes, RESPONSE_BODY.as_bytes());
+    assert!(matches!(
+      fold_inputs::<MAX_STACK_HEIGHT>(
+        &mock_manifest(),
+        plaintext.as_bytes(),
+        &[0; 32],
+        &NONCE,
+        1,
+        512,
+        3
+      ),
+      Err(WitnessGeneratorError::HttpMessage(HttpMessageError::ChunkedBody { offset: 47 }))
+    ));
+  }
+
+  #[test]
+  fn test_fold_inputs_truncated_body() {
+    let plaintext = format!(
+      "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+      RESPONSE_BODY.len(),
+      &RESPONSE_BODY[..RESPONSE_BODY.len() - 10]
+    );
+    assert!(matches!(
+      fold_inputs::<MAX_STACK_HEIGHT>(
+        &mock_manifest(),
+        plaintext.as_bytes(),
+        &[0; 32],
+        &NONCE,
+        1,
+        512,
+        3
+      ),
+      Err(WitnessGeneratorError::HttpMessage(HttpMessageError::ContentLengthMismatch { .. }))
+    ));
+  }
+
+  #[test]
+  fn test_fold_inputs_header_line_at_fold_boundary() {
+    // The start line and the padding header fill the first fold
+    let plaintext = format!(
+      "HTTP/1.1 200 OK\r\nx-padding: \
+       {}\r\n{RESPONSE_HEADER_0}\r\n{RESPONSE_HEADER_1}\r\n\r\n{RESPONSE_BODY}",
+      "a".repeat(DATA_BYTES - 30)
+    );
+    assert!(matches!(
+      fold_inputs::<MAX_STACK_HEIGHT>(
+        &mock_manifest(),
+        plaintext.as_bytes(),
+        &[0; 32],
+        &NONCE,
+        1,
+        DATA_BYTES,
+        10,
+      ),
+      Err(WitnessGeneratorError::HeaderLineAtFoldBoundary { offset: DATA_BYTES })
+    ));
+  }
+
+  #[test]
+  fn test_fold_inputs_rom_too_long() {
+    assert!(matches!(
+      fold_inputs::<MAX_STACK_HEIGHT>(
+        &mock_manifest(),
+        plaintext().as_bytes(),
+        &[0; 32],
+        &NONCE,
+        1,
+        DATA_BYTES,
+        7,
+      ),
+      Err(WitnessGeneratorError::RomTooLong { .. })
+    ));
+  }
+}
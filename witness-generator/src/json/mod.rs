@@ -134,6 +134,21 @@ impl<const MAX_STACK_HEIGHT: usize> RawJsonMachine<MAX_STACK_HEIGHT> {
     output[MAX_STACK_HEIGHT * 4 + 3] = self.escaped;
     output
   }
+
+  /// Inverse of [`Self::flatten`], e.g. to replay a fold from its `state` input.
+  pub fn unflatten(polynomial_input: F, state: &[F; MAX_STACK_HEIGHT * 4 + 4]) -> Self {
+    Self {
+      polynomial_input,
+      stack: std::array::from_fn(|idx| (state[2 * idx], state[2 * idx + 1])),
+      tree_hash: std::array::from_fn(|idx| {
+        (state[2 * idx + MAX_STACK_HEIGHT * 2], state[2 * idx + 1 + MAX_STACK_HEIGHT * 2])
+      }),
+      monomial: state[MAX_STACK_HEIGHT * 4],
+      parsing_string: state[MAX_STACK_HEIGHT * 4 + 1],
+      parsing_primitive: state[MAX_STACK_HEIGHT * 4 + 2],
+      escaped: state[MAX_STACK_HEIGHT * 4 + 3],
+    }
+  }
 }
 
 pub fn json_value_digest<const MAX_STACK_HEIGHT: usize>(
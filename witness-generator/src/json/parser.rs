@@ -64,27 +64,21 @@ impl<const MAX_STACK_HEIGHT: usize> From<JsonMachine<MAX_STACK_HEIGHT>>
   }
 }
 
-impl<const MAX_STACK_HEIGHT: usize> JsonMachine<MAX_STACK_HEIGHT> {
-  fn current_location(&self) -> Location {
-    for i in 0..MAX_STACK_HEIGHT {
-      if self.location[i] == Location::None {
-        if i == 0 {
-          return self.location[0];
-        }
-        return self.location[i - 1];
-      }
-    }
-    self.location[MAX_STACK_HEIGHT - 1]
+fn current_location(location: &[Location]) -> Location {
+  match pointer(location) {
+    0 => location[0],
+    pointer => location[pointer - 1],
   }
+}
 
-  fn pointer(&self) -> usize {
-    for i in 0..MAX_STACK_HEIGHT {
-      if self.location[i] == Location::None {
-        return i;
-      }
-    }
-    MAX_STACK_HEIGHT
-  }
+fn pointer(location: &[Location]) -> usize {
+  location.iter().position(|location| *location == Location::None).unwrap_or(location.len())
+}
+
+impl<const MAX_STACK_HEIGHT: usize> JsonMachine<MAX_STACK_HEIGHT> {
+  fn current_location(&self) -> Location { current_location(&self.location) }
+
+  fn pointer(&self) -> usize { pointer(&self.location) }
 
   fn write_to_label_stack(&mut self) {
     match self.status.clone() {
@@ -136,18 +130,57 @@ const PRIMITIVE: [u8; 23] = [
 ];
 const ESCAPE: u8 = 92;
 
-// Tell clippy to eat shit
-#[allow(clippy::too_many_lines)]
 pub fn parse<const MAX_STACK_HEIGHT: usize>(
   bytes: &[u8],
   polynomial_input: F, // Hash of ct
 ) -> Result<Vec<JsonMachine<MAX_STACK_HEIGHT>>, WitnessGeneratorError> {
-  let mut machine = JsonMachine::<MAX_STACK_HEIGHT> {
+  let machine = JsonMachine::<MAX_STACK_HEIGHT> {
     polynomial_input,
     status: Status::default(),
     location: [Location::default(); MAX_STACK_HEIGHT],
     label_stack: std::array::from_fn(|_| (String::new(), String::new())),
   };
+  parse_from(bytes, machine)
+}
+
+/// Flattened [`RawJsonMachine`] states at the start of each `DATA_BYTES` chunk of the HTTP body,
+/// i.e. the `state` of every `JSONExtraction` fold, each with its digest `step_in[8]`.
+///
+/// The first fold starts from the all-zero state, every other fold from the state after the last
+/// byte of the previous chunk.
+pub fn parse_folds<const MAX_STACK_HEIGHT: usize>(
+  body: &[u8],
+  data_bytes: usize,
+  polynomial_input: F,
+) -> Result<Vec<([F; MAX_STACK_HEIGHT * 4 + 4], F)>, WitnessGeneratorError>
+where
+  [(); MAX_STACK_HEIGHT * 4 + 4]:,
+{
+  if data_bytes == 0 {
+    return Err(WitnessGeneratorError::CatchAll("data bytes must be positive".to_string()));
+  }
+  let states = parse::<MAX_STACK_HEIGHT>(body, polynomial_input)?;
+  let folds = std::iter::once([F::ZERO; MAX_STACK_HEIGHT * 4 + 4])
+    .chain(
+      states
+        .into_iter()
+        .skip(data_bytes - 1)
+        .step_by(data_bytes)
+        .map(|machine| RawJsonMachine::from(machine).flatten()),
+    )
+    .take(body.len().div_ceil(data_bytes))
+    .map(|state| (state, field_polynomial_digest(&state, polynomial_input)))
+    .collect();
+  Ok(folds)
+}
+
+/// Continues parsing from `machine`, digesting labels with its polynomial input.
+// Tell clippy to eat shit
+#[allow(clippy::too_many_lines)]
+pub(crate) fn parse_from<const MAX_STACK_HEIGHT: usize>(
+  bytes: &[u8],
+  mut machine: JsonMachine<MAX_STACK_HEIGHT>,
+) -> Result<Vec<JsonMachine<MAX_STACK_HEIGHT>>, WitnessGeneratorError> {
   let mut output = vec![];
   // ctr used only for debuggin
   // let mut ctr = 0;
@@ -315,6 +348,283 @@ pub fn parse<const MAX_STACK_HEIGHT: usize>(
   Ok(output)
 }
 
+/// Status of a [`DigestJsonMachine`], the string or primitive being parsed is only known by its
+/// digest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DigestStatus {
+  None,
+  ParsingString(bool),
+  ParsingPrimitive,
+}
+
+/// A [`JsonMachine`] that only knows its labels by their digests, as the circuit does, so parsing
+/// can resume from a [`RawJsonMachine`].
+#[derive(Clone, Copy, Debug)]
+struct DigestJsonMachine<const MAX_STACK_HEIGHT: usize> {
+  polynomial_input: F,
+  status:           DigestStatus,
+  location:         [Location; MAX_STACK_HEIGHT],
+  label_stack:      [(F, F); MAX_STACK_HEIGHT],
+  /// Digest and monomial of the string or primitive being parsed, the monomial is zero while it is
+  /// empty
+  label:            (F, F),
+}
+
+/// Recovers the location encoded by a `stack` entry, `None` if it does not encode one.
+fn location_from_stack(entry: (F, F)) -> Option<Location> {
+  match field_element_to_u64(entry.0)? {
+    0 if entry.1 == F::ZERO => Some(Location::None),
+    1 if entry.1 == F::ZERO => Some(Location::ObjectKey),
+    1 if entry.1 == F::ONE => Some(Location::ObjectValue),
+    2 => Some(Location::ArrayIndex(field_element_to_u64(entry.1)?.try_into().ok()?)),
+    _ => None,
+  }
+}
+
+/// Fails with [`WitnessGeneratorError::InvalidJsonMachine`] on a state the parser never reaches.
+impl<const MAX_STACK_HEIGHT: usize> TryFrom<RawJsonMachine<MAX_STACK_HEIGHT>>
+  for DigestJsonMachine<MAX_STACK_HEIGHT>
+{
+  type Error = WitnessGeneratorError;
+
+  fn try_from(raw: RawJsonMachine<MAX_STACK_HEIGHT>) -> Result<Self, Self::Error> {
+    let invalid = |reason: &str| WitnessGeneratorError::InvalidJsonMachine(reason.to_string());
+    let mut location = [Location::None; MAX_STACK_HEIGHT];
+    for (location, entry) in location.iter_mut().zip(raw.stack) {
+      *location = location_from_stack(entry).ok_or_else(|| invalid("invalid stack entry"))?;
+    }
+    let pointer = pointer(&location);
+    if location[pointer..].iter().any(|location| *location != Location::None) {
+      return Err(invalid("stack entry above an empty one"));
+    }
+    if raw.tree_hash[pointer..].iter().any(|labels| *labels != (F::ZERO, F::ZERO)) {
+      return Err(invalid("label above the stack"));
+    }
+
+    let flag = |flag: F| match flag {
+      flag if flag == F::ZERO => Some(false),
+      flag if flag == F::ONE => Some(true),
+      _ => None,
+    };
+    let status = match (flag(raw.parsing_string), flag(raw.parsing_primitive), flag(raw.escaped)) {
+      (Some(false), Some(false), Some(false)) => DigestStatus::None,
+      (Some(true), Some(false), Some(escaped)) => DigestStatus::ParsingString(escaped),
+      (Some(false), Some(true), Some(false)) => DigestStatus::ParsingPrimitive,
+      _ => return Err(invalid("conflicting status flags")),
+    };
+
+    // While parsing, the label on top of the stack is the string or primitive parsed so far
+    let label = match (status, current_location(&location)) {
+      (DigestStatus::None, _) | (_, Location::None) if raw.monomial != F::ZERO =>
+        return Err(invalid("monomial outside of a label")),
+      (DigestStatus::None, _) | (_, Location::None) => (F::ZERO, F::ZERO),
+      (_, Location::ObjectKey) => (raw.tree_hash[pointer - 1].0, raw.monomial),
+      (_, Location::ObjectValue | Location::ArrayIndex(_)) =>
+        (raw.tree_hash[pointer - 1].1, raw.monomial),
+    };
+    if label.1 == F::ZERO && label.0 != F::ZERO {
+      return Err(invalid("monomial is zero in a nonempty label"));
+    }
+
+    Ok(Self {
+      polynomial_input: raw.polynomial_input,
+      status,
+      location,
+      label_stack: raw.tree_hash,
+      label,
+    })
+  }
+}
+
+impl<const MAX_STACK_HEIGHT: usize> From<DigestJsonMachine<MAX_STACK_HEIGHT>>
+  for RawJsonMachine<MAX_STACK_HEIGHT>
+{
+  fn from(value: DigestJsonMachine<MAX_STACK_HEIGHT>) -> Self {
+    let monomial = match (value.current_location(), value.status) {
+      (Location::None, _) | (_, DigestStatus::None) => F::ZERO,
+      _ => value.label.1,
+    };
+    let (parsing_string, parsing_primitive, escaped) = match value.status {
+      DigestStatus::None => (F::ZERO, F::ZERO, F::ZERO),
+      DigestStatus::ParsingString(escaped) => (F::ONE, F::ZERO, F::from(u64::from(escaped))),
+      DigestStatus::ParsingPrimitive => (F::ZERO, F::ONE, F::ZERO),
+    };
+    Self {
+      polynomial_input: value.polynomial_input,
+      stack: value.location.map(Into::into),
+      tree_hash: value.label_stack,
+      parsing_string,
+      parsing_primitive,
+      escaped,
+      monomial,
+    }
+  }
+}
+
+impl<const MAX_STACK_HEIGHT: usize> DigestJsonMachine<MAX_STACK_HEIGHT> {
+  fn current_location(&self) -> Location { current_location(&self.location) }
+
+  fn pointer(&self) -> usize { pointer(&self.location) }
+
+  fn push(&mut self, char: u8) {
+    self.label.1 =
+      if self.label.1 == F::ZERO { F::ONE } else { self.label.1 * self.polynomial_input };
+    self.label.0 += F::from(u64::from(char)) * self.label.1;
+  }
+
+  fn push_location(&mut self, location: Location) -> Result<(), WitnessGeneratorError> {
+    let pointer = self.pointer();
+    *self
+      .location
+      .get_mut(pointer)
+      .ok_or_else(|| WitnessGeneratorError::JsonParser("Stack overflow!".to_string()))? = location;
+    Ok(())
+  }
+
+  fn write_to_label_stack(&mut self) {
+    if self.status == DigestStatus::None {
+      return;
+    }
+    match self.current_location() {
+      Location::ArrayIndex(_) | Location::ObjectValue =>
+        self.label_stack[self.pointer() - 1].1 = self.label.0,
+      Location::ObjectKey => self.label_stack[self.pointer() - 1] = (self.label.0, F::ZERO),
+      Location::None => {},
+    }
+  }
+
+  fn clear_label_stack(&mut self) { self.label_stack[self.pointer()] = (F::ZERO, F::ZERO); }
+
+  fn clear_array_index_label(&mut self) {
+    if let Some(pointer) = self.pointer().checked_sub(1) {
+      self.label_stack[pointer] = (F::ZERO, F::ZERO);
+    }
+  }
+
+  /// Same transitions as [`parse_from`], on the label digests.
+  fn step(&mut self, char: u8) -> Result<(), WitnessGeneratorError> {
+    let error = |message: &str| Err(WitnessGeneratorError::JsonParser(message.to_string()));
+    match char {
+      START_BRACE => match (self.status, self.current_location()) {
+        (DigestStatus::None, Location::None | Location::ObjectValue | Location::ArrayIndex(_)) =>
+          self.push_location(Location::ObjectKey)?,
+        _ => return error("Start brace in invalid position!"),
+      },
+      END_BRACE => match (self.status, self.current_location()) {
+        (
+          DigestStatus::None | DigestStatus::ParsingPrimitive,
+          Location::ObjectKey | Location::ObjectValue,
+        ) => {
+          self.location[self.pointer() - 1] = Location::None;
+          self.status = DigestStatus::None;
+          self.clear_label_stack();
+        },
+        _ => return error("End brace in invalid position!"),
+      },
+      START_BRACKET => match (self.status, self.current_location()) {
+        (DigestStatus::None, Location::None | Location::ObjectValue | Location::ArrayIndex(_)) =>
+          self.push_location(Location::ArrayIndex(0))?,
+        _ => return error("Start bracket in invalid position!"),
+      },
+      END_BRACKET => match (self.status, self.current_location()) {
+        (DigestStatus::None | DigestStatus::ParsingPrimitive, Location::ArrayIndex(_)) => {
+          self.location[self.pointer() - 1] = Location::None;
+          self.status = DigestStatus::None;
+          self.clear_label_stack();
+        },
+        _ => return error("End bracket in invalid position!"),
+      },
+      COLON => match (self.status, self.current_location()) {
+        (DigestStatus::None, Location::ObjectKey) =>
+          self.location[self.pointer() - 1] = Location::ObjectValue,
+        (DigestStatus::ParsingString(_), _) => {
+          self.push(char);
+          self.status = DigestStatus::ParsingString(false);
+        },
+        _ => return error("Colon in invalid position!"),
+      },
+      COMMA => match (self.status, self.current_location()) {
+        (DigestStatus::None | DigestStatus::ParsingPrimitive, Location::ObjectValue) => {
+          self.location[self.pointer() - 1] = Location::ObjectKey;
+          self.status = DigestStatus::None;
+          self.clear_array_index_label();
+        },
+        (DigestStatus::None | DigestStatus::ParsingPrimitive, Location::ArrayIndex(idx)) => {
+          self.location[self.pointer() - 1] = Location::ArrayIndex(idx + 1);
+          self.status = DigestStatus::None;
+          self.clear_array_index_label();
+        },
+        _ => return error("Comma in invalid position!"),
+      },
+      QUOTE => match self.status {
+        DigestStatus::None => {
+          self.status = DigestStatus::ParsingString(false);
+          self.label = (F::ZERO, F::ZERO);
+        },
+        DigestStatus::ParsingString(false) => {
+          self.status = DigestStatus::None;
+          if let Location::ArrayIndex(_) | Location::ObjectValue = self.current_location() {
+            self.label_stack[self.pointer() - 1].1 = F::ZERO;
+          }
+        },
+        DigestStatus::ParsingString(true) => {
+          self.push(char);
+          self.status = DigestStatus::ParsingString(false);
+        },
+        DigestStatus::ParsingPrimitive => return error("Quote found while parsing number!"),
+      },
+      ESCAPE =>
+        if self.status == DigestStatus::ParsingString(false) {
+          self.status = DigestStatus::ParsingString(true);
+        },
+      c if PRIMITIVE.contains(&c) => match self.status {
+        DigestStatus::None => {
+          self.status = DigestStatus::ParsingPrimitive;
+          self.label = (F::ZERO, F::ZERO);
+          self.push(char);
+        },
+        DigestStatus::ParsingPrimitive => self.push(char),
+        DigestStatus::ParsingString(_) => {
+          self.push(char);
+          self.status = DigestStatus::ParsingString(false);
+        },
+      },
+      _ => match self.status {
+        DigestStatus::ParsingPrimitive => {
+          self.status = DigestStatus::None;
+          self.clear_array_index_label();
+        },
+        DigestStatus::ParsingString(_) => {
+          self.push(char);
+          self.status = DigestStatus::ParsingString(false);
+        },
+        DigestStatus::None => {},
+      },
+    }
+    self.write_to_label_stack();
+    Ok(())
+  }
+}
+
+/// Continues parsing from a circuit `state`, e.g. the `state` of a `JSONExtraction` fold, with the
+/// labels only known by their digests. Gives the same states as [`parse_from`] on the
+/// corresponding [`JsonMachine`].
+///
+/// Fails with [`WitnessGeneratorError::InvalidJsonMachine`] on a state the parser never reaches.
+pub(crate) fn parse_raw_from<const MAX_STACK_HEIGHT: usize>(
+  bytes: &[u8],
+  machine: RawJsonMachine<MAX_STACK_HEIGHT>,
+) -> Result<Vec<RawJsonMachine<MAX_STACK_HEIGHT>>, WitnessGeneratorError> {
+  let mut machine = DigestJsonMachine::try_from(machine)?;
+  bytes
+    .iter()
+    .map(|char| {
+      machine.step(*char)?;
+      Ok(RawJsonMachine::from(machine))
+    })
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -412,6 +722,23 @@ mod tests {
     verify_final_state(raw_states.last().unwrap());
   }
 
+  #[test]
+  fn test_parse_folds() {
+    let polynomial_input = create_polynomial_input();
+    let states = parse::<5>(SPOTIFY_EXAMPLE.as_bytes(), polynomial_input).unwrap();
+    let folds = parse_folds::<5>(SPOTIFY_EXAMPLE.as_bytes(), 32, polynomial_input).unwrap();
+
+    assert_eq!(folds.len(), SPOTIFY_EXAMPLE.len().div_ceil(32));
+    assert_eq!(folds[0], ([F::ZERO; 24], F::ZERO));
+    assert_eq!(folds[1].0, RawJsonMachine::from(states[31].clone()).flatten());
+    assert_ne!(folds[1].1, F::ZERO);
+    assert_eq!(RawJsonMachine::<5>::unflatten(polynomial_input, &folds[1].0).flatten(), folds[1].0);
+    for (state, digest) in folds {
+      assert_eq!(digest, field_polynomial_digest(&state, polynomial_input));
+    }
+    assert!(parse_folds::<5>(SPOTIFY_EXAMPLE.as_bytes(), 0, polynomial_input).is_err());
+  }
+
   #[test]
   fn test_json_parser_stack_overflow() {
     // Create deeply nested object that exceeds MAX_STACK_HEIGHT
@@ -453,6 +780,63 @@ mod tests {
       states.into_iter().map(RawJsonMachine::from).collect::<Vec<RawJsonMachine<12>>>();
     assert_eq!(raw_states.len(), input.len());
 
+    let mut initial_state = RawJsonMachine::<12>::initial_state();
+    initial_state.polynomial_input = polynomial_input;
+    let digest_states = parse_raw_from(&input, initial_state).unwrap();
+    assert!(digest_states.iter().zip(&raw_states).all(|(a, b)| a.flatten() == b.flatten()));
+
     verify_final_state(raw_states.last().unwrap());
   }
+
+  #[rstest]
+  #[case::spotify(SPOTIFY_EXAMPLE)]
+  #[case::array_only(r#"[ 42, { "a" : "b" } , [ 0 , 1 ] , "foobar"]"#)]
+  #[case::value_array_object(r#"{ "a" : [ { "b" : [ 1 , 4 ] } , { "c" : "b" } ] }"#)]
+  #[case::string_escape(r#"{"a": "\"b\"", "k": "x:y"}"#)]
+  #[case::primitives(
+    r#"{"null": null, "false": false, "true": true, "num1": 2.0E-1, "num2": 2.0e+1}"#
+  )]
+  #[case::empty(r#"{"object":{},"arr":[],"":""}"#)]
+  fn test_parse_raw_from(#[case] input: &str) {
+    let polynomial_input = create_polynomial_input();
+    let states = parse::<5>(input.as_bytes(), polynomial_input)
+      .unwrap()
+      .into_iter()
+      .map(|machine| RawJsonMachine::from(machine).flatten())
+      .collect::<Vec<_>>();
+
+    // Resuming from any state, only known by its flattened digests, gives the same states
+    for start in 0..states.len() {
+      let machine = RawJsonMachine::<5>::unflatten(polynomial_input, &states[start]);
+      let resumed = parse_raw_from(&input.as_bytes()[start + 1..], machine).unwrap();
+      assert_eq!(
+        resumed.iter().map(RawJsonMachine::flatten).collect::<Vec<_>>(),
+        states[start + 1..]
+      );
+    }
+  }
+
+  #[rstest]
+  #[case::stack_entry(|machine: &mut RawJsonMachine<5>| machine.stack[0] = (F::from(3), F::ZERO))]
+  #[case::stack_hole(|machine: &mut RawJsonMachine<5>| machine.stack[1] = (F::ONE, F::ZERO))]
+  #[case::label_above_stack(|machine: &mut RawJsonMachine<5>| machine.tree_hash[0].1 = F::ONE)]
+  #[case::flags(|machine: &mut RawJsonMachine<5>| {
+    machine.parsing_string = F::ONE;
+    machine.parsing_primitive = F::ONE;
+  })]
+  #[case::escaped(|machine: &mut RawJsonMachine<5>| machine.escaped = F::ONE)]
+  #[case::monomial(|machine: &mut RawJsonMachine<5>| machine.monomial = F::ONE)]
+  #[case::empty_label(|machine: &mut RawJsonMachine<5>| {
+    machine.stack[0] = (F::ONE, F::ZERO);
+    machine.tree_hash[0].0 = F::ONE;
+    machine.parsing_string = F::ONE;
+  })]
+  fn test_parse_raw_from_invalid(#[case] corrupt: fn(&mut RawJsonMachine<5>)) {
+    let mut machine = RawJsonMachine::<5>::initial_state();
+    corrupt(&mut machine);
+    assert!(matches!(
+      parse_raw_from(b"{}", machine),
+      Err(WitnessGeneratorError::InvalidJsonMachine(_))
+    ));
+  }
 }
@@ -3,10 +3,17 @@
 #![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
 
+pub mod chacha20;
+pub mod circuits;
+pub mod digest;
 pub mod error;
 pub mod http;
+pub mod inputs;
 pub mod json;
+pub mod manifest;
 #[cfg(test)] pub(crate) mod mock;
+pub mod public_io;
+pub mod rom;
 
 use std::collections::HashMap;
 
@@ -25,6 +32,15 @@ pub type E = client_side_prover::provider::Bn256EngineKZG;
 pub type G = <E as Engine>::GE;
 pub type F = <G as Group>::Scalar;
 
+/// Number of public IO variables (`step_in`/`step_out`) shared by all circuits.
+pub const PUBLIC_IO_LENGTH: usize = 11;
+
+/// Maximum JSON nesting depth supported by `JSONExtraction` in the `builds/` targets.
+pub const MAX_STACK_HEIGHT: usize = 12;
+
+/// Maximum number of headers `HTTPVerification` in the `builds/` targets can lock.
+pub const MAX_NUMBER_OF_HEADERS: usize = 25;
+
 /// Struct representing a byte or padding.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ByteOrPad {
@@ -71,6 +87,14 @@ pub fn field_element_to_base10_string(fe: F) -> String {
   BigUint::from_bytes_le(&fe.to_bytes()).to_str_radix(10)
 }
 
+/// Converts a field element to a `u64`, `None` if it does not fit.
+fn field_element_to_u64(fe: F) -> Option<u64> {
+  let bytes = fe.to_bytes();
+  let (low, high) = bytes.split_at(8);
+  high.iter().all(|byte| *byte == 0).then_some(())?;
+  Some(u64::from_le_bytes(low.try_into().ok()?))
+}
+
 impl Serialize for ByteOrPad {
   /// converts to field element using `to_field_element` and then to base10 string
   fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -162,6 +186,18 @@ pub fn polynomial_digest(bytes: &[u8], polynomial_input: F, counter: u64) -> F {
   accumulated
 }
 
+/// Polynomial digest of field elements, i.e. `sum(coefficients[i] * polynomial_input^i)`. This is
+/// the `PolynomialDigest(N)` template used to digest machine states.
+pub fn field_polynomial_digest(coefficients: &[F], polynomial_input: F) -> F {
+  let mut monomial = F::ONE;
+  let mut accumulated = F::ZERO;
+  for coefficient in coefficients {
+    accumulated += *coefficient * monomial;
+    monomial *= polynomial_input;
+  }
+  accumulated
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -248,4 +284,15 @@ mod tests {
       )
     );
   }
+
+  #[test]
+  fn test_field_polynomial_digest() {
+    let bytes = [1, 2, 3, 4, 5];
+    let coefficients = bytes.map(|byte| F::from(u64::from(byte)));
+    assert_eq!(
+      field_polynomial_digest(&coefficients, F::from(7)),
+      polynomial_digest(&bytes, F::from(7), 0)
+    );
+    assert_eq!(field_polynomial_digest(&[], F::from(7)), F::ZERO);
+  }
 }
@@ -0,0 +1,293 @@
+use std::fmt;
+
+use serde::{
+  de::{MapAccess, Visitor},
+  Deserializer,
+};
+
+use super::*;
+use crate::{http::Headers, json::JsonKey};
+
+/// The statements a web proof makes about an HTTP request/response pair.
+///
+/// Deserializes from the same JSON as the `Manifest` used by the circuit tests, i.e. headers as
+/// `Record<string, string[]>` and JSON keys either as [`JsonKey`] or as
+/// `{ "type": "Object" | "ArrayIndex", "value": ... }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+  pub request:  ManifestRequest,
+  pub response: ManifestResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestRequest {
+  pub method:  String,
+  pub url:     String,
+  pub version: String,
+  #[serde(default, deserialize_with = "deserialize_headers")]
+  pub headers: Headers,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestResponse {
+  pub version: String,
+  pub status:  String,
+  pub message: String,
+  #[serde(default, deserialize_with = "deserialize_headers")]
+  pub headers: Headers,
+  pub body:    ResponseBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseBody {
+  /// Key sequence leading to the JSON value to extract
+  #[serde(deserialize_with = "deserialize_json_sequence")]
+  pub json: Vec<JsonKey>,
+}
+
+impl Manifest {
+  /// Deserializes a manifest from JSON and checks it fits the circuits, see [`Manifest::validate`].
+  pub fn from_json(json: &[u8]) -> Result<Self, WitnessGeneratorError> {
+    let manifest: Self = serde_json::from_slice(json)?;
+    manifest.validate()?;
+    Ok(manifest)
+  }
+
+  /// Checks the manifest can be proven by the circuits:
+  /// - the JSON key sequence is not empty and at most [`MAX_STACK_HEIGHT`] deep
+  /// - request and response each lock at most [`MAX_NUMBER_OF_HEADERS`] headers
+  pub fn validate(&self) -> Result<(), WitnessGeneratorError> {
+    if self.response.body.json.is_empty() {
+      return Err(WitnessGeneratorError::InvalidManifest("json key sequence is empty".to_string()));
+    }
+    if self.response.body.json.len() > MAX_STACK_HEIGHT {
+      return Err(WitnessGeneratorError::InvalidManifest(format!(
+        "json key sequence of length {} exceeds max stack height {MAX_STACK_HEIGHT}",
+        self.response.body.json.len()
+      )));
+    }
+    for (direction, headers) in
+      [("request", &self.request.headers), ("response", &self.response.headers)]
+    {
+      if headers.len() > MAX_NUMBER_OF_HEADERS {
+        return Err(WitnessGeneratorError::InvalidManifest(format!(
+          "{direction} has {} headers, max is {MAX_NUMBER_OF_HEADERS}",
+          headers.len()
+        )));
+      }
+    }
+    Ok(())
+  }
+
+  /// [`Manifest::validate`] for a proof over both the request and the response, whose start lines
+  /// and headers must all fit the `MAX_NUMBER_OF_HEADERS + 1` main digests, as
+  /// [`combined_initial_digest`](crate::digest::combined_initial_digest) digests them together.
+  pub fn validate_combined(&self) -> Result<(), WitnessGeneratorError> {
+    self.validate()?;
+    let lines = 2 + self.request.headers.len() + self.response.headers.len();
+    if lines > MAX_NUMBER_OF_HEADERS + 1 {
+      return Err(WitnessGeneratorError::InvalidManifest(format!(
+        "{lines} start lines and headers exceed the {} main digests of the circuit",
+        MAX_NUMBER_OF_HEADERS + 1
+      )));
+    }
+    Ok(())
+  }
+}
+
+impl ManifestRequest {
+  /// Request line as it appears in the plaintext, e.g. `GET / HTTP/1.1`
+  pub fn start_line(&self) -> Vec<u8> {
+    format!("{} {} {}", self.method, self.url, self.version).into_bytes()
+  }
+}
+
+impl ManifestResponse {
+  /// Status line as it appears in the plaintext, e.g. `HTTP/1.1 200 OK`
+  pub fn start_line(&self) -> Vec<u8> {
+    format!("{} {} {}", self.version, self.status, self.message).into_bytes()
+  }
+}
+
+/// A header value given either as a string or as a list of strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HeaderValue {
+  Single(String),
+  Multiple(Vec<String>),
+}
+
+/// Deserializes headers keeping the order they are given in, with a line per value.
+fn deserialize_headers<'de, D>(deserializer: D) -> Result<Headers, D::Error>
+where D: Deserializer<'de> {
+  struct HeadersVisitor;
+
+  impl<'de> Visitor<'de> for HeadersVisitor {
+    type Value = Headers;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+      formatter.write_str("a map of header names to values")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Headers, A::Error>
+    where A: MapAccess<'de> {
+      let mut headers = Headers::new();
+      while let Some((name, value)) = map.next_entry::<String, HeaderValue>()? {
+        match value {
+          HeaderValue::Single(value) => headers.append(name, value),
+          HeaderValue::Multiple(values) =>
+            for value in values {
+              headers.append(name.clone(), value);
+            },
+        }
+      }
+      Ok(headers)
+    }
+  }
+
+  deserializer.deserialize_map(HeadersVisitor)
+}
+
+/// JSON key in the tagged form used by the TS tooling, where object keys are given as bytes
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "value")]
+enum JsonMaskType {
+  Object(Vec<u8>),
+  ArrayIndex(usize),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonKeyRepr {
+  Key(JsonKey),
+  Mask(JsonMaskType),
+}
+
+fn deserialize_json_sequence<'de, D>(deserializer: D) -> Result<Vec<JsonKey>, D::Error>
+where D: Deserializer<'de> {
+  Vec::<JsonKeyRepr>::deserialize(deserializer)?
+    .into_iter()
+    .map(|key| match key {
+      JsonKeyRepr::Key(key) => Ok(key),
+      JsonKeyRepr::Mask(JsonMaskType::Object(bytes)) =>
+        String::from_utf8(bytes).map(JsonKey::String).map_err(serde::de::Error::custom),
+      JsonKeyRepr::Mask(JsonMaskType::ArrayIndex(idx)) => Ok(JsonKey::Num(idx)),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // `JSON.stringify(MockManifest())` from `circuits/test/common/index.ts`
+  const MOCK_MANIFEST_JSON: &str = r#"{
+    "request": { "method": "GET", "url": "/", "version": "HTTP/1.1", "headers": {} },
+    "response": {
+      "status": "200",
+      "version": "HTTP/1.1",
+      "message": "OK",
+      "headers": {
+        "content-type": ["application/json; charset=utf-8"],
+        "content-encoding": ["gzip"]
+      },
+      "body": {
+        "json": [
+          { "type": "Object", "value": [100, 97, 116, 97] },
+          { "type": "Object", "value": [105, 116, 101, 109, 115] },
+          { "type": "ArrayIndex", "value": 0 },
+          { "type": "Object", "value": [112, 114, 111, 102, 105, 108, 101] },
+          { "type": "Object", "value": [110, 97, 109, 101] }
+        ]
+      }
+    }
+  }"#;
+
+  #[test]
+  fn test_start_lines() {
+    let manifest = mock_manifest();
+    assert_eq!(manifest.request.start_line(), b"GET / HTTP/1.1");
+    assert_eq!(manifest.response.start_line(), RESPONSE_START_LINE.as_bytes());
+  }
+
+  #[test]
+  fn test_manifest_from_ts_json() {
+    let manifest = Manifest::from_json(MOCK_MANIFEST_JSON.as_bytes()).unwrap();
+    assert_eq!(manifest, mock_manifest());
+  }
+
+  #[test]
+  fn test_manifest_header_order() {
+    let manifest = Manifest::from_json(MOCK_MANIFEST_JSON.as_bytes()).unwrap();
+    let names = manifest.response.headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["content-type", "content-encoding"]);
+
+    let swapped = MOCK_MANIFEST_JSON
+      .replace("content-type", "x-swap")
+      .replace("content-encoding", "content-type")
+      .replace("x-swap", "content-encoding");
+    let manifest = Manifest::from_json(swapped.as_bytes()).unwrap();
+    let names = manifest.response.headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["content-encoding", "content-type"]);
+  }
+
+  #[test]
+  fn test_manifest_roundtrip() {
+    let json = serde_json::to_vec(&mock_manifest()).unwrap();
+    assert_eq!(Manifest::from_json(&json).unwrap(), mock_manifest());
+  }
+
+  #[test]
+  fn test_manifest_multi_valued_header() {
+    let json = MOCK_MANIFEST_JSON.replace(r#"["gzip"]"#, r#"["gzip", "br"]"#);
+    let manifest = Manifest::from_json(json.as_bytes()).unwrap();
+    assert_eq!(manifest.response.headers.len(), 3);
+    assert_eq!(manifest.response.headers.get_all("content-encoding").collect::<Vec<_>>(), [
+      "gzip", "br"
+    ]);
+
+    let json = serde_json::to_vec(&manifest).unwrap();
+    assert_eq!(Manifest::from_json(&json).unwrap(), manifest);
+  }
+
+  #[test]
+  fn test_manifest_validate() {
+    let mut manifest = mock_manifest();
+    manifest.response.body.json = (0..MAX_STACK_HEIGHT + 1).map(JsonKey::Num).collect();
+    assert!(matches!(manifest.validate(), Err(WitnessGeneratorError::InvalidManifest(_))));
+
+    let mut manifest = mock_manifest();
+    manifest.request.headers =
+      (0..=MAX_NUMBER_OF_HEADERS).map(|idx| (format!("x-header-{idx}"), String::new())).collect();
+    assert!(matches!(manifest.validate(), Err(WitnessGeneratorError::InvalidManifest(_))));
+
+    let mut manifest = mock_manifest();
+    manifest.response.body.json = vec![];
+    assert!(matches!(manifest.validate(), Err(WitnessGeneratorError::InvalidManifest(_))));
+
+    // A response-only proof has all the main digests for the response
+    let mut manifest = mock_manifest();
+    manifest.response.headers =
+      (0..MAX_NUMBER_OF_HEADERS).map(|idx| (format!("x-header-{idx}"), String::new())).collect();
+    assert!(manifest.validate().is_ok());
+  }
+
+  #[test]
+  fn test_manifest_validate_combined() {
+    // Each direction fits on its own, but not both start lines and all headers together
+    let mut manifest = mock_manifest();
+    manifest.request.headers = (0..MAX_NUMBER_OF_HEADERS - manifest.response.headers.len())
+      .map(|idx| (format!("x-header-{idx}"), String::new()))
+      .collect();
+    assert!(manifest.validate().is_ok());
+    assert!(matches!(manifest.validate_combined(), Err(WitnessGeneratorError::InvalidManifest(_))));
+    manifest.request.headers = (0..MAX_NUMBER_OF_HEADERS - manifest.response.headers.len() - 1)
+      .map(|idx| (format!("x-header-{idx}"), String::new()))
+      .collect();
+    assert!(manifest.validate_combined().is_ok());
+
+    let mut manifest = mock_manifest();
+    manifest.response.body.json = vec![];
+    assert!(matches!(manifest.validate_combined(), Err(WitnessGeneratorError::InvalidManifest(_))));
+  }
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+/// The public IO (`step_in`/`step_out`) shared by every circuit of the NIVC program.
+///
+/// Fields are in slot order, i.e. `accumulator` is `step_in[0]` and `ciphertext_digest_seed` is
+/// `step_in[10]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(into = "[String; PUBLIC_IO_LENGTH]")]
+pub struct PublicIo {
+  /// Running sum of plaintext and extracted value digests, ends as the digest of the JSON value
+  pub accumulator:                 F,
+  /// Monomial of the next plaintext byte in `PlaintextAuthentication`
+  pub plaintext_monomial:          F,
+  /// Monomial of the next byte in `HTTPVerification`
+  pub http_monomial:               F,
+  /// Polynomial digest of the [`RawHttpMachine`](crate::http::RawHttpMachine) state
+  pub http_machine_state_digest:   F,
+  /// Sum of the hashed `main_digests`, i.e. the start lines and headers to lock
+  pub header_verification_lock:    F,
+  /// Number of start lines and headers that are still to be matched
+  pub num_matches:                 F,
+  /// Monomial of the next HTTP body byte
+  pub body_monomial:               F,
+  /// Monomial of the next byte in `JSONExtraction`
+  pub json_monomial:               F,
+  /// Polynomial digest of the [`RawJsonMachine`](crate::json::RawJsonMachine) state
+  pub json_machine_state_digest:   F,
+  /// Poseidon hash of the compressed digest of the JSON key sequence
+  pub json_sequence_digest_hashed: F,
+  /// Seed of [`data_hasher`] for the next ciphertext chunk
+  pub ciphertext_digest_seed:      F,
+}
+
+impl From<[F; PUBLIC_IO_LENGTH]> for PublicIo {
+  fn from(io: [F; PUBLIC_IO_LENGTH]) -> Self {
+    Self {
+      accumulator:                 io[0],
+      plaintext_monomial:          io[1],
+      http_monomial:               io[2],
+      http_machine_state_digest:   io[3],
+      header_verification_lock:    io[4],
+      num_matches:                 io[5],
+      body_monomial:               io[6],
+      json_monomial:               io[7],
+      json_machine_state_digest:   io[8],
+      json_sequence_digest_hashed: io[9],
+      ciphertext_digest_seed:      io[10],
+    }
+  }
+}
+
+impl From<PublicIo> for [F; PUBLIC_IO_LENGTH] {
+  fn from(io: PublicIo) -> Self {
+    [
+      io.accumulator,
+      io.plaintext_monomial,
+      io.http_monomial,
+      io.http_machine_state_digest,
+      io.header_verification_lock,
+      io.num_matches,
+      io.body_monomial,
+      io.json_monomial,
+      io.json_machine_state_digest,
+      io.json_sequence_digest_hashed,
+      io.ciphertext_digest_seed,
+    ]
+  }
+}
+
+/// Implement From<PublicIo> for [String; PUBLIC_IO_LENGTH]
+impl From<PublicIo> for [String; PUBLIC_IO_LENGTH] {
+  fn from(io: PublicIo) -> Self {
+    <[F; PUBLIC_IO_LENGTH]>::from(io).map(field_element_to_base10_string)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_public_io_layout() {
+    let io: [F; PUBLIC_IO_LENGTH] = std::array::from_fn(|idx| F::from(idx as u64));
+    let public_io = PublicIo::from(io);
+    assert_eq!(public_io.accumulator, F::from(0));
+    assert_eq!(public_io.http_machine_state_digest, F::from(3));
+    assert_eq!(public_io.body_monomial, F::from(6));
+    assert_eq!(public_io.json_sequence_digest_hashed, F::from(9));
+    assert_eq!(public_io.ciphertext_digest_seed, F::from(10));
+    assert_eq!(<[F; PUBLIC_IO_LENGTH]>::from(public_io), io);
+  }
+
+  #[test]
+  fn test_public_io_serialize() {
+    let public_io = PublicIo { num_matches: F::from(3), ..Default::default() };
+    let expected: [String; PUBLIC_IO_LENGTH] =
+      std::array::from_fn(|idx| if idx == 5 { "3".to_string() } else { "0".to_string() });
+    assert_eq!(serde_json::to_value(public_io).unwrap(), serde_json::to_value(expected).unwrap());
+  }
+}
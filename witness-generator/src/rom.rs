@@ -0,0 +1,153 @@
+//! Planning the ROM, i.e. the ordered circuits folded over a response.
+
+use std::ops::Range;
+
+use super::*;
+use crate::chacha20::BLOCK_SIZE;
+
+/// The circuits of the NIVC program, with the index they have in the setup made by `create-pp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitType {
+  PlaintextAuthentication = 0,
+  HttpVerification = 1,
+  JsonExtraction = 2,
+}
+
+impl CircuitType {
+  /// Index of the circuit in the setup
+  pub fn index(self) -> usize { self as usize }
+
+  /// Base name of the circuit artifacts, e.g. `plaintext_authentication_512b.r1cs`
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::PlaintextAuthentication => "plaintext_authentication",
+      Self::HttpVerification => "http_verification",
+      Self::JsonExtraction => "json_extraction",
+    }
+  }
+}
+
+/// A single fold of the ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomStep {
+  pub circuit: CircuitType,
+  /// Bytes of the plaintext (or ciphertext) folded in this step
+  pub range:   Range<usize>,
+  /// Number of [`ByteOrPad::Pad`] appended to the chunk to fill `DATA_BYTES`
+  pub padding: usize,
+}
+
+impl RomStep {
+  /// The chunk of `bytes` folded in this step, padded to `DATA_BYTES`
+  pub fn padded_chunk(&self, bytes: &[u8]) -> Vec<ByteOrPad> {
+    ByteOrPad::from_bytes_with_padding(&bytes[self.range.clone()], self.padding)
+  }
+}
+
+/// Plans the folds proving a response: `PlaintextAuthentication` and `HTTPVerification` over the
+/// whole plaintext, then `JSONExtraction` over the body, each in chunks of `data_bytes` with the
+/// last chunk padded.
+///
+/// Boundaries are placed by length only, [`parse_folds`](crate::http::parser::parse_folds) rejects
+/// a plaintext where one lands at the start of a header line.
+///
+/// # Arguments
+/// - `plaintext_len`: length of the response plaintext
+/// - `ciphertext_len`: length of the response ciphertext, equal to the plaintext length
+/// - `body_offset`: index of the first body byte in the plaintext
+/// - `data_bytes`: `DATA_BYTES` of the circuits, a multiple of the ChaCha20 block size
+/// - `max_rom_length`: the max ROM length the setup was made with
+pub fn plan_rom(
+  plaintext_len: usize,
+  ciphertext_len: usize,
+  body_offset: usize,
+  data_bytes: usize,
+  max_rom_length: usize,
+) -> Result<Vec<RomStep>, WitnessGeneratorError> {
+  if plaintext_len != ciphertext_len {
+    return Err(WitnessGeneratorError::CatchAll(format!(
+      "plaintext has {plaintext_len} bytes but ciphertext has {ciphertext_len}"
+    )));
+  }
+  if data_bytes == 0 || !data_bytes.is_multiple_of(BLOCK_SIZE) {
+    return Err(WitnessGeneratorError::CatchAll(format!(
+      "data bytes {data_bytes} is not a positive multiple of {BLOCK_SIZE}"
+    )));
+  }
+  if body_offset > plaintext_len {
+    return Err(WitnessGeneratorError::CatchAll(format!(
+      "body offset {body_offset} is past the end of the plaintext"
+    )));
+  }
+
+  let chunks = |circuit: CircuitType, range: Range<usize>| {
+    (range.start..range.end).step_by(data_bytes).map(move |start| {
+      let end = range.end.min(start + data_bytes);
+      RomStep { circuit, range: start..end, padding: data_bytes - (end - start) }
+    })
+  };
+  let rom = chunks(CircuitType::PlaintextAuthentication, 0..plaintext_len)
+    .chain(chunks(CircuitType::HttpVerification, 0..plaintext_len))
+    .chain(chunks(CircuitType::JsonExtraction, body_offset..plaintext_len))
+    .collect::<Vec<_>>();
+
+  if rom.len() > max_rom_length {
+    return Err(WitnessGeneratorError::RomTooLong { length: rom.len(), max_rom_length });
+  }
+  Ok(rom)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BODY_OFFSET: usize = RESPONSE_PLAINTEXT.len() - RESPONSE_BODY.len();
+
+  #[test]
+  fn test_plan_rom_single_chunk() {
+    let rom =
+      plan_rom(RESPONSE_PLAINTEXT.len(), RESPONSE_CIPHERTEXT.len(), BODY_OFFSET, 512, 3).unwrap();
+    assert_eq!(rom, [
+      RomStep { circuit: CircuitType::PlaintextAuthentication, range: 0..320, padding: 192 },
+      RomStep { circuit: CircuitType::HttpVerification, range: 0..320, padding: 192 },
+      RomStep {
+        circuit: CircuitType::JsonExtraction,
+        range:   BODY_OFFSET..320,
+        padding: 512 - RESPONSE_BODY.len(),
+      },
+    ]);
+    assert_eq!(rom[2].padded_chunk(RESPONSE_PLAINTEXT.as_bytes()), {
+      let body = RESPONSE_BODY.as_bytes();
+      ByteOrPad::from_bytes_with_padding(body, 512 - body.len())
+    });
+  }
+
+  #[test]
+  fn test_plan_rom_multiple_chunks() {
+    let rom =
+      plan_rom(RESPONSE_PLAINTEXT.len(), RESPONSE_CIPHERTEXT.len(), BODY_OFFSET, 128, 10).unwrap();
+    let circuits = rom.iter().map(|step| step.circuit.index()).collect::<Vec<_>>();
+    assert_eq!(circuits, [0, 0, 0, 1, 1, 1, 2, 2]);
+    assert_eq!(rom[0].range, 0..128);
+    assert_eq!(rom[2], RomStep {
+      circuit: CircuitType::PlaintextAuthentication,
+      range:   256..320,
+      padding: 64,
+    });
+    assert_eq!(rom[6].range, BODY_OFFSET..BODY_OFFSET + 128);
+    assert_eq!(rom[7].range.end, 320);
+    assert!(rom.iter().all(|step| step.range.len() + step.padding == 128));
+  }
+
+  #[test]
+  fn test_plan_rom_errors() {
+    let len = RESPONSE_PLAINTEXT.len();
+    assert!(matches!(
+      plan_rom(len, len, BODY_OFFSET, 128, 7),
+      Err(WitnessGeneratorError::RomTooLong { length: 8, max_rom_length: 7 })
+    ));
+    assert!(plan_rom(len, len - 1, BODY_OFFSET, 128, 10).is_err());
+    assert!(plan_rom(len, len, BODY_OFFSET, 100, 10).is_err());
+    assert!(plan_rom(len, len, len + 1, 128, 10).is_err());
+  }
+}